@@ -2,26 +2,45 @@
 use cosmwasm_std::entry_point;
 
 use cosmwasm_std::{
-   to_binary, Addr, AllBalanceResponse, BankMsg, BankQuery, Coin, CosmosMsg, DepsMut, Env,
-   MessageInfo, QueryRequest, Response, Storage, Uint128, Uint64, WasmMsg,
+   from_binary, to_binary, Addr, AllBalanceResponse, BankMsg, BankQuery, Binary, Coin, CosmosMsg,
+   Deps, DepsMut, Env, MessageInfo, Order, QueryRequest, Response, StdResult, Storage, Uint128,
+   Uint256, WasmMsg,
 };
 use cw2::set_contract_version;
 use cw20::{
-   BalanceResponse as Cw20BalanceResponse, Cw20ExecuteMsg, Cw20QueryMsg, TokenInfoResponse,
+   AllowanceResponse, BalanceResponse as Cw20BalanceResponse, Cw20ExecuteMsg, Cw20QueryMsg,
+   Cw20ReceiveMsg, Denom,
 };
+use cw721::{Cw721QueryMsg, OwnerOfResponse};
+use cw721_base::{ExecuteMsg as Cw721BaseExecuteMsg, Extension, MintMsg};
+use cw_storage_plus::Bound;
+use sha2::Digest;
 
 use crate::error::ContractError;
-use crate::state::{CONFIG, JUNO_PRICE, TOTAL, USDC_PRICE, USERS, VEST_PARAM};
-use Interface::vesting::{Config, ExecuteMsg, InstantiateMsg, UserInfo, VestingParameter};
+use crate::state::{
+   ledger, ACCEPTED_CW20S, ACCEPTED_DENOMS, CATEGORIES, CATEGORY_ALLOCATED, CONFIG, CONTRIBUTIONS,
+   CW20_CONTRIBUTIONS, ESCROW, FUNDED, LEDGER_COUNT, MERKLE_ROOT, NFT_CONTRACT, ORACLE,
+   OWNERSHIP_PROPOSAL, PAUSED, PAUSER, POSITION_NFTS, PRO_RATA_SETTLED, RAISED, REFUND_RESERVE,
+   RELEASED, ROUNDS, ROUND_SOLD, SALE_CONFIG, SALE_RESULT, SOLD, TOTAL, USERS, VEST_PARAM,
+};
+use Interface::oracle::{PriceResponse, QueryMsg as OracleQueryMsg};
+use Interface::vesting::{
+   AcceptedDenom, Category, CategoryAmount, Config, ContributionRecord, ContributionSource,
+   Cw20HookMsg, ExecuteMsg, InstantiateMsg, OracleConfig, OwnershipProposal, PauseFlags, Round,
+   RoundAmount, SaleConfig, SaleResult, SaleStatus, SolvencyInfo, UserInfo, VestingParameter,
+   VestingSchedule, WhitelistProof,
+};
 
 // version info for migration info
 const CONTRACT_NAME: &str = "AquaVesting";
 const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
 
-const JUNO_DENOM: &str = "ujunox";
-const USDC_DENOM: &str = "ibc/EAC38D55372F38F1AFD68DF7FE9EF762DCF69F26520643CF3F9D292A738D8034";
+const AQUA_PRICE: u128 = 30; //1000, used while no rounds are defined
+const AQUA_DECIMALS: u32 = 6;
+const BASIS_POINTS: u64 = 10_000;
 
-const AQUA_PRICE: u128 = 30; //1000
+pub const DEFAULT_LIMIT: u32 = 10;
+pub const MAX_LIMIT: u32 = 30;
 
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn instantiate(
@@ -52,17 +71,37 @@ pub fn instantiate(
 
    VEST_PARAM.save(
       deps.storage,
-      &VestingParameter {
+      &VestingSchedule::Standard(VestingParameter {
          soon: Uint128::zero(),
          after: Uint128::zero(),
          period: Uint128::new(7_776_000), //0%: tge, 0: after, 3 months: priod
-      },
+      }),
    )?;
 
    TOTAL.save(deps.storage, &Uint128::new(0))?;
+   RELEASED.save(deps.storage, &Uint128::zero())?;
+   ESCROW.save(deps.storage, &false)?;
+   FUNDED.save(deps.storage, &Uint128::zero())?;
+   PAUSED.save(deps.storage, &PauseFlags::default())?;
+
+   SALE_CONFIG.save(
+      deps.storage,
+      &SaleConfig {
+         hard_cap: None,
+         soft_cap: Uint128::zero(),
+         soft_cap_deadline: Uint128::zero(),
+         min_purchase: Uint128::zero(),
+         max_allocation: None,
+         sale_start: Uint128::zero(),
+         sale_end: None,
+         pro_rata: false,
+      },
+   )?;
+   SOLD.save(deps.storage, &Uint128::zero())?;
 
-   USDC_PRICE.save(deps.storage, &Uint128::new(1000))?;
-   JUNO_PRICE.save(deps.storage, &Uint128::new(5280))?;
+   for accepted_denom in msg.accepted_denoms {
+      ACCEPTED_DENOMS.save(deps.storage, accepted_denom.denom.clone(), &accepted_denom)?;
+   }
    Ok(Response::new().add_attribute("method", "instantiate"))
 }
 
@@ -74,29 +113,109 @@ pub fn execute(
    msg: ExecuteMsg,
 ) -> Result<Response, ContractError> {
    match msg {
-      ExecuteMsg::StartRelease { start_time } => try_startrelease(deps, info, start_time),
+      ExecuteMsg::StartRelease {
+         start_time,
+         require_solvency,
+      } => try_startrelease(deps, env, info, start_time, require_solvency),
+
+      ExecuteMsg::SetPrice { denom, price } => try_setprice(deps, info, denom, price),
+
+      ExecuteMsg::SetAcceptedDenom { accepted_denom } => {
+         try_setaccepteddenom(deps, info, accepted_denom)
+      }
+
+      ExecuteMsg::RemoveAcceptedDenom { denom } => try_removeaccepteddenom(deps, info, denom),
+
+      ExecuteMsg::SetOracle { oracle } => try_setoracle(deps, info, oracle),
+
+      ExecuteMsg::SetEscrow { enabled } => try_setescrow(deps, info, enabled),
+
+      ExecuteMsg::SetPauser { pauser } => try_setpauser(deps, info, pauser),
 
-      ExecuteMsg::SetPrice {
-         usdc_price,
-         juno_price,
-      } => try_setprice(deps, info, usdc_price, juno_price),
+      ExecuteMsg::SetPause { paused } => try_setpause(deps, info, paused),
+
+      ExecuteMsg::SetNftContract { nft_addr } => try_setnftcontract(deps, info, nft_addr),
+
+      ExecuteMsg::MintPositionNft {} => try_mintpositionnft(deps, info),
+
+      ExecuteMsg::WithdrawEscrow { wallet, amount } => {
+         try_withdrawescrow(deps, info, wallet, amount)
+      }
+
+      ExecuteMsg::SetAcceptedCw20 { accepted_cw20 } => {
+         try_setacceptedcw20(deps, info, accepted_cw20)
+      }
+
+      ExecuteMsg::RemoveAcceptedCw20 { contract_addr } => {
+         try_removeacceptedcw20(deps, info, contract_addr)
+      }
 
       ExecuteMsg::SetConfig {
-         admin,
          treasury,
          token_addr,
-         start_time,
-      } => try_setconfig(deps, info, admin, treasury, token_addr, start_time),
+      } => try_setconfig(deps, info, treasury, token_addr),
+
+      ExecuteMsg::ProposeNewOwner { owner, expires_at } => {
+         try_proposenewowner(deps, env, info, owner, expires_at)
+      }
+
+      ExecuteMsg::AcceptOwnership {} => try_acceptownership(deps, env, info),
+
+      ExecuteMsg::CancelOwnershipProposal {} => try_cancelownershipproposal(deps, info),
 
       ExecuteMsg::SetVestingParameters { params } => try_setvestingparameters(deps, info, params),
 
-      ExecuteMsg::AddUser {} => try_adduser(deps, info),
+      ExecuteMsg::SetUserVestingParameters { wallet, params } => {
+         try_setuservestingparameters(deps, info, wallet, params)
+      }
+
+      ExecuteMsg::RevokeUser { wallet } => try_revokeuser(deps, env, info, wallet),
+
+      ExecuteMsg::SetSaleConfig { sale_config } => try_setsaleconfig(deps, info, sale_config),
 
-      ExecuteMsg::AddUserByOwner { wallet, amount } => {
-         try_adduser_byowner(deps, info, wallet, amount)
+      ExecuteMsg::SetRound { round_id, round } => try_setround(deps, info, round_id, round),
+
+      ExecuteMsg::RemoveRound { round_id } => try_removeround(deps, info, round_id),
+
+      ExecuteMsg::SetCategory { name, category } => try_setcategory(deps, info, name, category),
+
+      ExecuteMsg::RemoveCategory { name } => try_removecategory(deps, info, name),
+
+      ExecuteMsg::RegisterMerkleRoot { merkle_root } => {
+         try_registermerkleroot(deps, info, merkle_root)
+      }
+
+      ExecuteMsg::AddUser {
+         whitelist,
+         min_aqua_out,
+      } => try_adduser(deps, env, info, whitelist, min_aqua_out),
+
+      ExecuteMsg::AddUserByOwner {
+         wallet,
+         amount,
+         vest_param,
+         category,
+      } => try_adduser_byowner(deps, env, info, wallet, amount, vest_param, category),
+
+      ExecuteMsg::Receive(msg) => try_receive(deps, env, info, msg),
+
+      ExecuteMsg::ClaimPendingTokens {
+         position,
+         recipient,
+         msg,
+      } => try_claimpendingtokens(deps, env, info, position, recipient, msg),
+
+      ExecuteMsg::DistributePending { start_after, limit } => {
+         try_distributepending(deps, env, info, start_after, limit)
       }
 
-      ExecuteMsg::ClaimPendingTokens {} => try_claimpendingtokens(deps, env, info),
+      ExecuteMsg::ClaimRefund {} => try_claimrefund(deps, env, info),
+
+      ExecuteMsg::FinalizeSale {} => try_finalizesale(deps, env, info),
+
+      ExecuteMsg::TransferPosition { recipient, amount } => {
+         try_transferposition(deps, info, recipient, amount)
+      }
 
       ExecuteMsg::Withdraw { wallet } => try_withdraw(deps, env, info, wallet),
    }
@@ -104,14 +223,37 @@ pub fn execute(
 
 pub fn try_startrelease(
    deps: DepsMut,
+   env: Env,
    info: MessageInfo,
    start_time: Uint128,
+   require_solvency: Option<bool>,
 ) -> Result<Response, ContractError> {
    let mut config = CONFIG.load(deps.storage)?;
    if config.owner != info.sender && config.treasury != info.sender {
       return Err(ContractError::Unauthorized {});
    }
 
+   if PAUSED.load(deps.storage)?.admin {
+      return Err(ContractError::Paused {});
+   }
+
+   let sale_config = SALE_CONFIG.load(deps.storage)?;
+   if SOLD.load(deps.storage)? < sale_config.soft_cap {
+      return Err(ContractError::SoftCapNotReached {});
+   }
+   if sale_config.pro_rata && SALE_RESULT.may_load(deps.storage)?.is_none() {
+      return Err(ContractError::SaleNotFinalized {});
+   }
+   if require_solvency.unwrap_or(false) {
+      let solvency = solvency(deps.as_ref(), &env)?;
+      if !solvency.balance_shortfall.is_zero() || !solvency.allowance_shortfall.is_zero() {
+         return Err(ContractError::Insolvent {
+            balance_shortfall: solvency.balance_shortfall,
+            allowance_shortfall: solvency.allowance_shortfall,
+         });
+      }
+   }
+
    config.start_time = start_time;
    CONFIG.save(deps.storage, &config)?;
    Ok(Response::new().add_attribute("action", "Start Release"))
@@ -120,192 +262,495 @@ pub fn try_startrelease(
 pub fn try_setvestingparameters(
    deps: DepsMut,
    info: MessageInfo,
-   params: VestingParameter,
+   params: VestingSchedule,
 ) -> Result<Response, ContractError> {
    let config = CONFIG.load(deps.storage).unwrap();
    if config.owner != info.sender && config.treasury != info.sender {
       return Err(ContractError::Unauthorized {});
    }
+   validate_schedule(&params)?;
 
    VEST_PARAM.save(deps.storage, &params)?;
    Ok(Response::new().add_attribute("action", "Set Vesting parameters"))
 }
 
-pub fn calc_pending(store: &dyn Storage, env: Env, user: &UserInfo) -> Uint128 {
-   let config = CONFIG.load(store).unwrap();
-   if config.start_time == Uint128::zero() {
-      return Uint128::zero();
-   }
-
-   let vest_param = VEST_PARAM.load(store).unwrap();
-
-   let past_time = Uint128::new(env.block.time.seconds() as u128) - config.start_time;
-
-   let mut unlocked = Uint128::zero();
-   if past_time > Uint128::zero() {
-      unlocked = user.total_amount * vest_param.soon / Uint128::new(100);
+pub fn try_setuservestingparameters(
+   deps: DepsMut,
+   info: MessageInfo,
+   wallet: String,
+   params: Option<VestingSchedule>,
+) -> Result<Response, ContractError> {
+   let config = CONFIG.load(deps.storage).unwrap();
+   if config.owner != info.sender && config.treasury != info.sender {
+      return Err(ContractError::Unauthorized {});
    }
-   let locked = user.total_amount - unlocked;
-   if past_time > vest_param.after {
-      unlocked += (past_time - vest_param.after) * locked / vest_param.period;
-      if unlocked >= locked {
-         unlocked = locked;
-      }
+   if let Some(params) = &params {
+      validate_schedule(params)?;
    }
 
-   return unlocked - user.released_amount;
+   let address = deps.api.addr_validate(wallet.as_str())?;
+   let mut user_info = USERS.load(deps.storage, address.clone())?;
+   user_info.vest_param = params;
+   USERS.save(deps.storage, address, &user_info)?;
+   Ok(Response::new().add_attribute("action", "Set user Vesting parameters"))
 }
 
-pub fn try_claimpendingtokens(
+pub fn try_revokeuser(
    deps: DepsMut,
    env: Env,
    info: MessageInfo,
+   wallet: String,
 ) -> Result<Response, ContractError> {
-   let mut user_info = USERS.load(deps.storage, info.sender.clone())?;
-   let mut pending_amount = calc_pending(deps.storage, env.clone(), &user_info);
-   if pending_amount == Uint128::zero() {
-      return Err(ContractError::NoPendingTokens {});
+   let config = CONFIG.load(deps.storage).unwrap();
+   if config.owner != info.sender && config.treasury != info.sender {
+      return Err(ContractError::Unauthorized {});
    }
 
-   user_info.released_amount += pending_amount;
-   USERS.save(deps.storage, info.sender.clone(), &user_info)?;
+   if PAUSED.load(deps.storage)?.admin {
+      return Err(ContractError::Paused {});
+   }
 
-   let config = CONFIG.load(deps.storage)?;
-   let token_balance: Cw20BalanceResponse = deps.querier.query_wasm_smart(
-      config.token_addr.clone(),
-      &Cw20QueryMsg::Balance {
-         address: config.treasury.clone(),
-      },
-   )?;
-   if token_balance.balance < pending_amount {
-      return Err(ContractError::NotEnoughBalance {});
+   let address = deps.api.addr_validate(wallet.as_str())?;
+   let mut user_info = USERS.load(deps.storage, address.clone())?;
+   if user_info.revocable_amount.is_zero() {
+      return Err(ContractError::NotRevocable {});
    }
+   let msgs = settle_pro_rata(deps.storage, &address, &mut user_info)?;
 
-   let bank_cw20 = WasmMsg::Execute {
-      contract_addr: config.token_addr,
-      msg: to_binary(&Cw20ExecuteMsg::TransferFrom {
-         owner: config.treasury,
-         recipient: info.sender.to_string(),
-         amount: pending_amount,
-      })
-      .unwrap(),
-      funds: Vec::new(),
-   };
+   //-----------what vested until now stays claimable, the rest goes back to the treasury-------
+   let mut vested = Uint128::zero();
+   let mut revoked = Uint128::zero();
+   let mut rest_amount = user_info.revocable_amount;
+   for category_amount in user_info.category_amounts.iter() {
+      let part = sub_position(&user_info, category_amount.amount, vec![category_amount.clone()]);
+      let unlocked = unlocked_amount(deps.storage, &env, &part);
+      let unvested = category_amount.amount - unlocked;
+      let allocated = CATEGORY_ALLOCATED
+         .may_load(deps.storage, category_amount.category.clone())?
+         .unwrap_or_default();
+      CATEGORY_ALLOCATED.save(
+         deps.storage,
+         category_amount.category.clone(),
+         &allocated.saturating_sub(unvested),
+      )?;
+      rest_amount = rest_amount.saturating_sub(category_amount.amount);
+      vested += unlocked;
+      revoked += unvested;
+   }
+   let part = sub_position(&user_info, rest_amount, Vec::new());
+   let unlocked = unlocked_amount(deps.storage, &env, &part);
+   vested += unlocked;
+   revoked += rest_amount - unlocked;
+
+   user_info.total_amount -= revoked;
+   user_info.vested_amount += vested;
+   user_info.revocable_amount = Uint128::zero();
+   user_info.category_amounts.clear();
+   match user_info.total_amount.is_zero() {
+      true => USERS.remove(deps.storage, address),
+      false => USERS.save(deps.storage, address, &user_info)?,
+   }
+
+   let total = TOTAL.load(deps.storage)?;
+   TOTAL.save(deps.storage, &(total - revoked))?;
 
    Ok(Response::new()
-      .add_message(CosmosMsg::Wasm(bank_cw20))
-      .add_attribute("action", "Claim pending tokens"))
+      .add_messages(msgs)
+      .add_attribute("action", "Revoke user")
+      .add_attribute("revoked", revoked.to_string()))
 }
 
-fn get_aqua_amount(storage: &dyn Storage, fund: &Coin) -> (bool, Uint128) {
-   if fund.denom == USDC_DENOM {
-      let usdc_price = USDC_PRICE.load(storage).unwrap();
-      let amount = fund.amount.u128() * usdc_price.u128() * 1_000 / AQUA_PRICE / 1_000;
-      return (true, Uint128::new(amount));
-   } else if fund.denom == JUNO_DENOM {
-      let juno_price = JUNO_PRICE.load(storage).unwrap();
-      let amount = fund.amount.u128() * juno_price.u128() * 1_000 / AQUA_PRICE / 1_000;
-      return (true, Uint128::new(amount));
+//part of a position vesting as it does within the position
+fn sub_position(
+   user_info: &UserInfo,
+   total_amount: Uint128,
+   category_amounts: Vec<CategoryAmount>,
+) -> UserInfo {
+   UserInfo {
+      total_amount,
+      released_amount: Uint128::zero(),
+      bought_amount: Uint128::zero(),
+      round_amounts: Vec::new(),
+      category_amounts,
+      revocable_amount: Uint128::zero(),
+      vested_amount: Uint128::zero(),
+      vest_param: user_info.vest_param.clone(),
    }
-   (false, Uint128::zero())
 }
-pub fn try_adduser(deps: DepsMut, info: MessageInfo) -> Result<Response, ContractError> {
-   if info.funds.len() == 0 {
-      return Err(ContractError::NeedFunds {});
+
+pub fn try_setsaleconfig(
+   deps: DepsMut,
+   info: MessageInfo,
+   sale_config: SaleConfig,
+) -> Result<Response, ContractError> {
+   let config = CONFIG.load(deps.storage)?;
+   if config.owner != info.sender && config.treasury != info.sender {
+      return Err(ContractError::Unauthorized {});
    }
 
-   let (is_support, amount) = get_aqua_amount(deps.storage, &info.funds[0]);
-   if !is_support {
-      return Err(ContractError::NotSupportToken {});
+   SALE_CONFIG.save(deps.storage, &sale_config)?;
+   Ok(Response::new().add_attribute("action", "Set Sale config"))
+}
+
+pub fn try_setround(
+   deps: DepsMut,
+   info: MessageInfo,
+   round_id: u64,
+   round: Round,
+) -> Result<Response, ContractError> {
+   let config = CONFIG.load(deps.storage)?;
+   if config.owner != info.sender && config.treasury != info.sender {
+      return Err(ContractError::Unauthorized {});
    }
 
-   let mut user_info = USERS
-      .may_load(deps.storage, info.sender.clone())?
-      .unwrap_or(UserInfo {
-         total_amount: Uint128::zero(),
-         released_amount: Uint128::zero(),
-      });
-   user_info.total_amount += amount;
+   if round.end_time <= round.start_time || round.aqua_price.is_zero() {
+      return Err(ContractError::InvalidRound {});
+   }
+   if let Some(vest_param) = &round.vest_param {
+      validate_schedule(vest_param)?;
+   }
 
-   USERS.save(deps.storage, info.sender, &user_info)?;
-   let mut total = TOTAL.load(deps.storage)?;
-   total += amount;
-   TOTAL.save(deps.storage, &total)?;
+   //-----------buyers keep the terms they bought at----------
+   if ROUND_SOLD.may_load(deps.storage, round_id)?.is_some() {
+      return Err(ContractError::RoundHasSales {});
+   }
 
-   Ok(Response::new().add_attribute("action", "Add  User info"))
+   //-----------at most one round is active at a time-------------
+   for item in ROUNDS.range(deps.storage, None, None, Order::Ascending) {
+      let (other_id, other) = item?;
+      if other_id != round_id
+         && other.start_time <= round.end_time
+         && round.start_time <= other.end_time
+      {
+         return Err(ContractError::RoundOverlap { round_id: other_id });
+      }
+   }
+
+   ROUNDS.save(deps.storage, round_id, &round)?;
+   Ok(Response::new()
+      .add_attribute("action", "Set Round")
+      .add_attribute("round_id", round_id.to_string()))
 }
-pub fn try_adduser_byowner(
+
+pub fn try_removeround(
    deps: DepsMut,
    info: MessageInfo,
-   wallet: String,
-   amount: Uint128,
+   round_id: u64,
 ) -> Result<Response, ContractError> {
-   //-----------check owner--------------------------
-   let config = CONFIG.load(deps.storage).unwrap();
+   let config = CONFIG.load(deps.storage)?;
    if config.owner != info.sender && config.treasury != info.sender {
       return Err(ContractError::Unauthorized {});
    }
 
-   let address = deps.api.addr_validate(wallet.as_str()).unwrap();
-   let mut user_info = USERS
-      .may_load(deps.storage, address.clone())?
-      .unwrap_or(UserInfo {
-         total_amount: Uint128::zero(),
-         released_amount: Uint128::zero(),
-      });
-   user_info.total_amount += amount;
+   if ROUND_SOLD.may_load(deps.storage, round_id)?.is_some() {
+      return Err(ContractError::RoundHasSales {});
+   }
 
-   USERS.save(deps.storage, address, &user_info)?;
-   let mut total = TOTAL.load(deps.storage)?;
-   total += amount;
-   TOTAL.save(deps.storage, &total)?;
+   ROUNDS.remove(deps.storage, round_id);
+   Ok(Response::new()
+      .add_attribute("action", "Remove Round")
+      .add_attribute("round_id", round_id.to_string()))
+}
+
+pub fn try_setcategory(
+   deps: DepsMut,
+   info: MessageInfo,
+   name: String,
+   category: Category,
+) -> Result<Response, ContractError> {
+   let config = CONFIG.load(deps.storage)?;
+   if config.owner != info.sender && config.treasury != info.sender {
+      return Err(ContractError::Unauthorized {});
+   }
+
+   if name.is_empty() {
+      return Err(ContractError::InvalidCategory {});
+   }
+   validate_schedule(&category.vest_param)?;
 
-   Ok(Response::new().add_attribute("action", "Add  User info"))
+   //-----------allocated wallets keep the terms they were given----------
+   if CATEGORY_ALLOCATED.may_load(deps.storage, name.clone())?.is_some() {
+      return Err(ContractError::CategoryHasAllocations {});
+   }
+
+   CATEGORIES.save(deps.storage, name.clone(), &category)?;
+   Ok(Response::new()
+      .add_attribute("action", "Set Category")
+      .add_attribute("name", name))
 }
-pub fn try_setconfig(
+
+pub fn try_removecategory(
    deps: DepsMut,
    info: MessageInfo,
-   admin: String,
-   treasury: String,
-   token_addr: String,
-   start_time: Uint128,
+   name: String,
 ) -> Result<Response, ContractError> {
-   //-----------check owner--------------------------
-   let mut config = CONFIG.load(deps.storage).unwrap();
-   if info.sender != config.owner {
+   let config = CONFIG.load(deps.storage)?;
+   if config.owner != info.sender && config.treasury != info.sender {
       return Err(ContractError::Unauthorized {});
    }
 
-   config.owner = deps.api.addr_validate(admin.as_str())?;
-   config.treasury = treasury;
-   config.token_addr = token_addr;
-   config.start_time = start_time;
+   if CATEGORY_ALLOCATED.may_load(deps.storage, name.clone())?.is_some() {
+      return Err(ContractError::CategoryHasAllocations {});
+   }
 
-   CONFIG.save(deps.storage, &config)?;
-   Ok(Response::new().add_attribute("action", "SetConfig"))
+   CATEGORIES.remove(deps.storage, name.clone());
+   Ok(Response::new()
+      .add_attribute("action", "Remove Category")
+      .add_attribute("name", name))
 }
 
-pub fn try_setprice(
+pub fn active_round(store: &dyn Storage, env: &Env) -> StdResult<Option<(u64, Round)>> {
+   let now = Uint128::new(env.block.time.seconds() as u128);
+   for item in ROUNDS.range(store, None, None, Order::Ascending) {
+      let (round_id, round) = item?;
+      if round.start_time <= now && now <= round.end_time {
+         return Ok(Some((round_id, round)));
+      }
+   }
+   Ok(None)
+}
+
+pub fn try_registermerkleroot(
    deps: DepsMut,
    info: MessageInfo,
-   usdc_price: Uint128,
-   juno_price: Uint128,
+   merkle_root: Option<String>,
 ) -> Result<Response, ContractError> {
    let config = CONFIG.load(deps.storage)?;
    if config.owner != info.sender && config.treasury != info.sender {
       return Err(ContractError::Unauthorized {});
    }
 
-   USDC_PRICE.save(deps.storage, &usdc_price)?;
-   JUNO_PRICE.save(deps.storage, &juno_price)?;
-   Ok(Response::new().add_attribute("action", "SetPrice"))
+   match merkle_root {
+      Some(merkle_root) => {
+         let mut root_buf: [u8; 32] = [0; 32];
+         hex::decode_to_slice(merkle_root.as_str(), &mut root_buf)
+            .map_err(|_| ContractError::InvalidMerkleRoot {})?;
+         MERKLE_ROOT.save(deps.storage, &merkle_root)?;
+      }
+      None => MERKLE_ROOT.remove(deps.storage),
+   }
+   Ok(Response::new().add_attribute("action", "Register merkle root"))
 }
 
-pub fn try_withdraw(
+//leaf is sha256(wallet + cap), pairs are hashed in sorted order up to the root
+fn verify_whitelist(
+   storage: &dyn Storage,
+   wallet: &Addr,
+   whitelist: Option<WhitelistProof>,
+) -> Result<Option<Uint128>, ContractError> {
+   let merkle_root = match MERKLE_ROOT.may_load(storage)? {
+      Some(merkle_root) => merkle_root,
+      None => return Ok(None),
+   };
+   let whitelist = whitelist.ok_or(ContractError::NotWhitelisted {})?;
+
+   let leaf = match whitelist.cap {
+      Some(cap) => format!("{}{}", wallet, cap),
+      None => wallet.to_string(),
+   };
+   let hash: [u8; 32] = sha2::Sha256::digest(leaf.as_bytes()).into();
+   let hash = whitelist
+      .proof
+      .iter()
+      .try_fold(hash, |hash, proof| -> Result<[u8; 32], ContractError> {
+         let mut proof_buf: [u8; 32] = [0; 32];
+         hex::decode_to_slice(proof.as_str(), &mut proof_buf)
+            .map_err(|_| ContractError::InvalidProof {})?;
+         let mut hashes = [hash, proof_buf];
+         hashes.sort_unstable();
+         Ok(sha2::Sha256::digest(&hashes.concat()).into())
+      })?;
+
+   let mut root_buf: [u8; 32] = [0; 32];
+   hex::decode_to_slice(merkle_root.as_str(), &mut root_buf)
+      .map_err(|_| ContractError::InvalidMerkleRoot {})?;
+   if root_buf != hash {
+      return Err(ContractError::NotWhitelisted {});
+   }
+
+   Ok(whitelist.cap)
+}
+
+pub fn sale_status(sale_config: &SaleConfig, env: &Env) -> SaleStatus {
+   let now = Uint128::new(env.block.time.seconds() as u128);
+   if now < sale_config.sale_start {
+      return SaleStatus::Upcoming;
+   }
+   match sale_config.sale_end {
+      Some(sale_end) if now > sale_end => SaleStatus::Closed,
+      _ => SaleStatus::Open,
+   }
+}
+
+pub fn is_sale_failed(store: &dyn Storage, env: &Env) -> StdResult<bool> {
+   let sale_config = SALE_CONFIG.load(store)?;
+   let sold = SOLD.load(store)?;
+   let now = Uint128::new(env.block.time.seconds() as u128);
+
+   Ok(sold < sale_config.soft_cap && now > sale_config.soft_cap_deadline)
+}
+
+//schedules have to unlock over a non-zero time, checkpoints monotonically up to 100%
+pub fn validate_schedule(vest_param: &VestingSchedule) -> Result<(), ContractError> {
+   let valid = match vest_param {
+      VestingSchedule::Standard(param) => !param.period.is_zero() && param.soon.u128() <= 100,
+      VestingSchedule::Linear { period } => !period.is_zero(),
+      VestingSchedule::Periodic { interval, steps } => !interval.is_zero() && *steps > 0,
+      VestingSchedule::Checkpoints(checkpoints) => {
+         let increasing = checkpoints
+            .windows(2)
+            .all(|pair| pair[0].time < pair[1].time && pair[0].unlocked <= pair[1].unlocked);
+         let complete = checkpoints.last().map(|last| last.unlocked) == Some(BASIS_POINTS);
+         increasing && complete
+      }
+   };
+   match valid {
+      true => Ok(()),
+      false => Err(ContractError::InvalidSchedule {}),
+   }
+}
+
+pub fn calc_unlocked(amount: Uint128, vest_param: &VestingSchedule, past_time: Uint128) -> Uint128 {
+   match vest_param {
+      VestingSchedule::Standard(vest_param) => {
+         let mut unlocked = Uint128::zero();
+         if past_time > Uint128::zero() {
+            unlocked = amount * vest_param.soon / Uint128::new(100);
+         }
+         let locked = amount - unlocked;
+         if past_time > vest_param.after {
+            let vested = (past_time - vest_param.after).multiply_ratio(locked, vest_param.period);
+            unlocked += vested.min(locked);
+         }
+         unlocked
+      }
+      VestingSchedule::Linear { period } => past_time.min(*period).multiply_ratio(amount, *period),
+      VestingSchedule::Periodic { interval, steps } => {
+         let passed = (past_time.u128() / interval.u128()).min(*steps as u128);
+         amount.multiply_ratio(passed, *steps as u128)
+      }
+      VestingSchedule::Checkpoints(checkpoints) => {
+         let (mut prev_time, mut prev_unlocked) = (Uint128::zero(), 0u128);
+         for checkpoint in checkpoints {
+            let unlocked = checkpoint.unlocked as u128;
+            if past_time < checkpoint.time {
+               let span = (checkpoint.time - prev_time).u128();
+               let elapsed = (past_time - prev_time).u128();
+               return amount.multiply_ratio(
+                  prev_unlocked * span + (unlocked - prev_unlocked) * elapsed,
+                  BASIS_POINTS as u128 * span,
+               );
+            }
+            prev_time = checkpoint.time;
+            prev_unlocked = unlocked;
+         }
+         amount.multiply_ratio(prev_unlocked, BASIS_POINTS as u128)
+      }
+   }
+}
+
+pub fn calc_pending(store: &dyn Storage, env: Env, user: &UserInfo) -> Uint128 {
+   unlocked_amount(store, &env, user).saturating_sub(user.released_amount)
+}
+
+fn unlocked_amount(store: &dyn Storage, env: &Env, user: &UserInfo) -> Uint128 {
+   let config = CONFIG.load(store).unwrap();
+   let now = Uint128::new(env.block.time.seconds() as u128);
+
+   //amounts vested before a revocation are unlocked already
+   let mut unlocked = user.vested_amount;
+   let mut rest_amount = user.total_amount.saturating_sub(user.vested_amount);
+
+   //amounts in categories vest by the category, from its own start if it has one
+   for category_amount in user.category_amounts.iter() {
+      let category = CATEGORIES
+         .load(store, category_amount.category.clone())
+         .unwrap();
+      rest_amount = rest_amount.saturating_sub(category_amount.amount);
+      let start_time = category.start_time.unwrap_or(config.start_time);
+      if start_time != Uint128::zero() {
+         let past_time = now.saturating_sub(start_time);
+         unlocked += calc_unlocked(category_amount.amount, &category.vest_param, past_time);
+      }
+   }
+
+   if config.start_time == Uint128::zero() {
+      return unlocked;
+   }
+
+   let vest_param = VEST_PARAM.load(store).unwrap();
+
+   let past_time = now.saturating_sub(config.start_time);
+
+   //a wallet with its own vesting parameters vests the rest by them
+   if let Some(user_param) = &user.vest_param {
+      unlocked += calc_unlocked(rest_amount, user_param, past_time);
+      return unlocked;
+   }
+
+   //amounts bought in rounds with their own vesting parameters vest separately
+   let mut global_amount = rest_amount;
+   for round_amount in user.round_amounts.iter() {
+      let round = ROUNDS.load(store, round_amount.round_id).unwrap();
+      if let Some(round_param) = round.vest_param {
+         unlocked += calc_unlocked(round_amount.amount, &round_param, past_time);
+         global_amount = global_amount.saturating_sub(round_amount.amount);
+      }
+   }
+   unlocked += calc_unlocked(global_amount, &vest_param, past_time);
+
+   unlocked
+}
+
+pub fn try_claimpendingtokens(
    deps: DepsMut,
    env: Env,
    info: MessageInfo,
-   wallet: String,
+   position: Option<String>,
+   recipient: Option<String>,
+   msg: Option<Binary>,
+) -> Result<Response, ContractError> {
+   if PAUSED.load(deps.storage)?.claims {
+      return Err(ContractError::Paused {});
+   }
+
+   //-----------claim rights follow the position NFT--------------------
+   let position = match position {
+      Some(position) => deps.api.addr_validate(position.as_str())?,
+      None => info.sender.clone(),
+   };
+   if position_owner(deps.as_ref(), &position)? != info.sender {
+      return Err(ContractError::Unauthorized {});
+   }
+   let recipient = match recipient {
+      Some(recipient) => deps.api.addr_validate(recipient.as_str())?,
+      None => info.sender.clone(),
+   };
+
+   let mut user_info = USERS.load(deps.storage, position.clone())?;
+   let refund_msgs = settle_pro_rata(deps.storage, &position, &mut user_info)?;
+   let pending_amount = calc_pending(deps.storage, env.clone(), &user_info);
+   if pending_amount == Uint128::zero() {
+      return Err(ContractError::NoPendingTokens {});
+   }
+
+   user_info.released_amount += pending_amount;
+   USERS.save(deps.storage, position, &user_info)?;
+
+   let bank_cw20 = release_tokens(deps, &recipient, pending_amount, msg)?;
+
+   Ok(Response::new()
+      .add_message(bank_cw20)
+      .add_messages(refund_msgs)
+      .add_attribute("action", "Claim pending tokens"))
+}
+
+pub fn try_distributepending(
+   mut deps: DepsMut,
+   env: Env,
+   info: MessageInfo,
+   start_after: Option<String>,
+   limit: Option<u32>,
 ) -> Result<Response, ContractError> {
    //-----------check owner--------------------------
    let config = CONFIG.load(deps.storage).unwrap();
@@ -313,6 +758,1447 @@ pub fn try_withdraw(
       return Err(ContractError::Unauthorized {});
    }
 
+   if PAUSED.load(deps.storage)?.claims {
+      return Err(ContractError::Paused {});
+   }
+
+   let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+   let start_after = match start_after {
+      Some(wallet) => Some(deps.api.addr_validate(wallet.as_str())?),
+      None => None,
+   };
+   let wallets: Vec<Addr> = USERS
+      .keys(
+         deps.storage,
+         start_after.map(Bound::exclusive),
+         None,
+         Order::Ascending,
+      )
+      .take(limit)
+      .collect::<StdResult<_>>()?;
+
+   let mut msgs: Vec<CosmosMsg> = Vec::new();
+   let mut distributed = Uint128::zero();
+   for wallet in wallets.iter() {
+      let mut user_info = USERS.load(deps.storage, wallet.clone())?;
+      msgs.extend(settle_pro_rata(deps.storage, wallet, &mut user_info)?);
+      //-----------keep the pro-rata cut even when nothing is pending yet-----
+      let pending_amount = calc_pending(deps.storage, env.clone(), &user_info);
+      user_info.released_amount += pending_amount;
+      USERS.save(deps.storage, wallet.clone(), &user_info)?;
+      if pending_amount.is_zero() {
+         continue;
+      }
+
+      let owner = position_owner(deps.as_ref(), wallet)?;
+      msgs.push(release_tokens(deps.branch(), &owner, pending_amount, None)?);
+      distributed += pending_amount;
+   }
+
+   //-----------the last wallet to resume from, none when all are done-----
+   let last_wallet = match wallets.len() == limit {
+      true => wallets.last().map(|wallet| wallet.to_string()),
+      false => None,
+   };
+   Ok(Response::new()
+      .add_messages(msgs)
+      .add_attribute("action", "Distribute pending tokens")
+      .add_attribute("distributed", distributed)
+      .add_attribute("last_wallet", last_wallet.unwrap_or_default()))
+}
+
+pub fn try_setnftcontract(
+   deps: DepsMut,
+   info: MessageInfo,
+   nft_addr: Option<String>,
+) -> Result<Response, ContractError> {
+   //-----------check owner--------------------------
+   let config = CONFIG.load(deps.storage).unwrap();
+   if config.owner != info.sender && config.treasury != info.sender {
+      return Err(ContractError::Unauthorized {});
+   }
+
+   match nft_addr {
+      Some(nft_addr) => {
+         let nft_addr = deps.api.addr_validate(nft_addr.as_str())?;
+         NFT_CONTRACT.save(deps.storage, &nft_addr)?;
+      }
+      None => NFT_CONTRACT.remove(deps.storage),
+   }
+   Ok(Response::new().add_attribute("action", "Set nft contract"))
+}
+
+//mints the NFT of an existing position to its wallet
+pub fn try_mintpositionnft(deps: DepsMut, info: MessageInfo) -> Result<Response, ContractError> {
+   if NFT_CONTRACT.may_load(deps.storage)?.is_none() {
+      return Err(ContractError::NftDisabled {});
+   }
+   USERS.load(deps.storage, info.sender.clone())?;
+
+   Ok(Response::new()
+      .add_messages(mint_position(deps.storage, &info.sender)?)
+      .add_attribute("action", "Mint position nft"))
+}
+
+//the NFT mint of a new position while positions are represented as NFTs
+fn mint_position(storage: &mut dyn Storage, wallet: &Addr) -> StdResult<Option<CosmosMsg>> {
+   let nft_addr = match NFT_CONTRACT.may_load(storage)? {
+      Some(nft_addr) => nft_addr,
+      None => return Ok(None),
+   };
+   if POSITION_NFTS.has(storage, wallet.clone()) {
+      return Ok(None);
+   }
+   POSITION_NFTS.save(storage, wallet.clone(), &nft_addr)?;
+
+   let mint = Cw721BaseExecuteMsg::Mint(MintMsg::<Extension> {
+      token_id: wallet.to_string(),
+      owner: wallet.to_string(),
+      token_uri: None,
+      extension: None,
+   });
+   Ok(Some(CosmosMsg::Wasm(WasmMsg::Execute {
+      contract_addr: nft_addr.to_string(),
+      msg: to_binary(&mint)?,
+      funds: Vec::new(),
+   })))
+}
+
+//the holder of a position, which is the owner of its NFT once minted
+pub fn position_owner(deps: Deps, wallet: &Addr) -> StdResult<Addr> {
+   let nft_addr = match POSITION_NFTS.may_load(deps.storage, wallet.clone())? {
+      Some(nft_addr) => nft_addr,
+      None => return Ok(wallet.clone()),
+   };
+
+   let owner: OwnerOfResponse = deps.querier.query_wasm_smart(
+      nft_addr,
+      &Cw721QueryMsg::OwnerOf {
+         token_id: wallet.to_string(),
+         include_expired: None,
+      },
+   )?;
+   deps.api.addr_validate(owner.owner.as_str())
+}
+
+//pay released AQUA from the escrow, or from the treasury allowance otherwise,
+//sending it into the recipient contract when a hook message is given
+fn release_tokens(
+   deps: DepsMut,
+   recipient: &Addr,
+   amount: Uint128,
+   hook: Option<Binary>,
+) -> Result<CosmosMsg, ContractError> {
+   RELEASED.update(deps.storage, |released| -> StdResult<_> { Ok(released + amount) })?;
+
+   let config = CONFIG.load(deps.storage)?;
+   let msg = match ESCROW.load(deps.storage)? {
+      true => {
+         let funded = FUNDED.load(deps.storage)?;
+         if funded < amount {
+            return Err(ContractError::NotEnoughBalance {});
+         }
+         FUNDED.save(deps.storage, &(funded - amount))?;
+
+         match hook {
+            Some(msg) => Cw20ExecuteMsg::Send {
+               contract: recipient.to_string(),
+               amount,
+               msg,
+            },
+            None => Cw20ExecuteMsg::Transfer {
+               recipient: recipient.to_string(),
+               amount,
+            },
+         }
+      }
+      false => {
+         let token_balance: Cw20BalanceResponse = deps.querier.query_wasm_smart(
+            config.token_addr.clone(),
+            &Cw20QueryMsg::Balance {
+               address: config.treasury.clone(),
+            },
+         )?;
+         if token_balance.balance < amount {
+            return Err(ContractError::NotEnoughBalance {});
+         }
+
+         match hook {
+            Some(msg) => Cw20ExecuteMsg::SendFrom {
+               owner: config.treasury,
+               contract: recipient.to_string(),
+               amount,
+               msg,
+            },
+            None => Cw20ExecuteMsg::TransferFrom {
+               owner: config.treasury,
+               recipient: recipient.to_string(),
+               amount,
+            },
+         }
+      }
+   };
+
+   Ok(CosmosMsg::Wasm(WasmMsg::Execute {
+      contract_addr: config.token_addr,
+      msg: to_binary(&msg)?,
+      funds: Vec::new(),
+   }))
+}
+
+//AQUA still owed to the holders
+pub fn obligations(store: &dyn Storage) -> StdResult<Uint128> {
+   let total = TOTAL.load(store)?;
+   let released = RELEASED.load(store)?;
+   Ok(total.saturating_sub(released))
+}
+
+//how far the treasury balance and allowance, or the escrow, cover the obligations
+pub fn solvency(deps: Deps, env: &Env) -> StdResult<SolvencyInfo> {
+   let config = CONFIG.load(deps.storage)?;
+   let total = TOTAL.load(deps.storage)?;
+   let released = RELEASED.load(deps.storage)?;
+   let obligations = total.saturating_sub(released);
+   let escrow = ESCROW.load(deps.storage)?;
+
+   let (balance, allowance) = match escrow {
+      true => (FUNDED.load(deps.storage)?, Uint128::zero()),
+      false => {
+         let token_balance: Cw20BalanceResponse = deps.querier.query_wasm_smart(
+            config.token_addr.clone(),
+            &Cw20QueryMsg::Balance {
+               address: config.treasury.clone(),
+            },
+         )?;
+         let allowance: AllowanceResponse = deps.querier.query_wasm_smart(
+            config.token_addr,
+            &Cw20QueryMsg::Allowance {
+               owner: config.treasury,
+               spender: env.contract.address.to_string(),
+            },
+         )?;
+         let allowance = match allowance.expires.is_expired(&env.block) {
+            true => Uint128::zero(),
+            false => allowance.allowance,
+         };
+         (token_balance.balance, allowance)
+      }
+   };
+
+   Ok(SolvencyInfo {
+      total,
+      released,
+      obligations,
+      escrow,
+      balance,
+      allowance,
+      balance_shortfall: obligations.saturating_sub(balance),
+      allowance_shortfall: match escrow {
+         true => Uint128::zero(),
+         false => obligations.saturating_sub(allowance),
+      },
+   })
+}
+
+pub fn load_accepted_denom(store: &dyn Storage, denom: &Denom) -> StdResult<Option<AcceptedDenom>> {
+   match denom {
+      Denom::Native(denom) => ACCEPTED_DENOMS.may_load(store, denom.clone()),
+      Denom::Cw20(contract_addr) => ACCEPTED_CW20S.may_load(store, contract_addr.clone()),
+   }
+}
+
+//oracle price when the oracle mode is on, checked against the price set by the admin
+fn get_token_price(
+   deps: Deps,
+   env: &Env,
+   denom: &Denom,
+   accepted_denom: &AcceptedDenom,
+) -> Result<Uint128, ContractError> {
+   let oracle = match ORACLE.may_load(deps.storage)? {
+      Some(oracle) => oracle,
+      None => return Ok(accepted_denom.price),
+   };
+
+   let feed: PriceResponse = deps.querier.query_wasm_smart(
+      oracle.oracle_addr,
+      &OracleQueryMsg::Price {
+         denom: denom_str(denom),
+      },
+   )?;
+
+   let now = Uint128::new(env.block.time.seconds() as u128);
+   if now.saturating_sub(feed.last_updated) > oracle.max_age {
+      return Err(ContractError::StalePrice {});
+   }
+
+   let deviation = if feed.price > accepted_denom.price {
+      feed.price - accepted_denom.price
+   } else {
+      accepted_denom.price - feed.price
+   };
+   if deviation * Uint128::new(100) > accepted_denom.price * oracle.max_deviation {
+      return Err(ContractError::PriceDeviation {});
+   }
+
+   Ok(feed.price)
+}
+
+//support flag, token price used and AQUA amount for the paid amount
+fn get_aqua_amount(
+   deps: Deps,
+   env: &Env,
+   denom: &Denom,
+   paid: Uint128,
+   aqua_price: u128,
+) -> Result<(bool, Uint128, Uint128), ContractError> {
+   let accepted_denom = match load_accepted_denom(deps.storage, denom)? {
+      Some(accepted_denom) => accepted_denom,
+      None => return Ok((false, Uint128::zero(), Uint128::zero())),
+   };
+   let price = get_token_price(deps, env, denom, &accepted_denom)?;
+   let amount = paid.u128() * price.u128() * 10u128.pow(AQUA_DECIMALS)
+      / aqua_price
+      / 10u128.pow(accepted_denom.decimals as u32);
+   Ok((true, price, Uint128::new(amount)))
+}
+
+//append a record to the contribution ledger, assigning its id and time
+fn record_contribution(
+   storage: &mut dyn Storage,
+   env: &Env,
+   mut record: ContributionRecord,
+) -> StdResult<()> {
+   let id = LEDGER_COUNT.may_load(storage)?.unwrap_or_default() + 1;
+   record.id = id;
+   record.time = Uint128::new(env.block.time.seconds() as u128);
+   ledger().save(storage, id, &record)?;
+   LEDGER_COUNT.save(storage, &id)
+}
+
+fn denom_str(denom: &Denom) -> String {
+   match denom {
+      Denom::Native(denom) => denom.clone(),
+      Denom::Cw20(contract_addr) => contract_addr.to_string(),
+   }
+}
+
+pub fn transfer_msg(recipient: &Addr, denom: &Denom, amount: Uint128) -> CosmosMsg {
+   match denom {
+      Denom::Native(denom) => CosmosMsg::Bank(BankMsg::Send {
+         to_address: recipient.to_string(),
+         amount: vec![Coin::new(amount.u128(), denom.clone())],
+      }),
+      Denom::Cw20(contract_addr) => CosmosMsg::Wasm(WasmMsg::Execute {
+         contract_addr: contract_addr.to_string(),
+         msg: to_binary(&Cw20ExecuteMsg::Transfer {
+            recipient: recipient.to_string(),
+            amount,
+         })
+         .unwrap(),
+         funds: Vec::new(),
+      }),
+   }
+}
+
+fn add_contribution(
+   storage: &mut dyn Storage,
+   wallet: &Addr,
+   denom: &Denom,
+   amount: Uint128,
+) -> StdResult<()> {
+   let add = |paid: Option<Uint128>| -> StdResult<_> { Ok(paid.unwrap_or_default() + amount) };
+   match denom {
+      Denom::Native(denom) => {
+         CONTRIBUTIONS.update(storage, (wallet.clone(), denom.clone()), add)?;
+      }
+      Denom::Cw20(contract_addr) => {
+         CW20_CONTRIBUTIONS.update(storage, (wallet.clone(), contract_addr.clone()), add)?;
+      }
+   }
+   RAISED.update(storage, denom_str(denom), add)?;
+   Ok(())
+}
+
+//refunds the part of the wallet's contributions not covered by kept / of
+//rounded up, so kept payments never fall short of the AQUA credited for them
+fn multiply_ratio_ceil(amount: Uint128, numerator: Uint128, denominator: Uint128) -> Uint128 {
+   let floor = amount.multiply_ratio(numerator, denominator);
+   match (amount.full_mul(numerator) % Uint256::from(denominator)).is_zero() {
+      true => floor,
+      false => floor + Uint128::new(1),
+   }
+}
+
+fn refund_contributions(
+   storage: &mut dyn Storage,
+   wallet: &Addr,
+   kept: Uint128,
+   of: Uint128,
+) -> StdResult<Vec<CosmosMsg>> {
+   let contributions: Vec<(String, Uint128)> = CONTRIBUTIONS
+      .prefix(wallet.clone())
+      .range(storage, None, None, Order::Ascending)
+      .collect::<StdResult<_>>()?;
+   let cw20_contributions: Vec<(Addr, Uint128)> = CW20_CONTRIBUTIONS
+      .prefix(wallet.clone())
+      .range(storage, None, None, Order::Ascending)
+      .collect::<StdResult<_>>()?;
+
+   let mut refunds: Vec<(Denom, Uint128)> = Vec::new();
+   for (denom, paid) in contributions {
+      let kept_paid = multiply_ratio_ceil(paid, kept, of);
+      if kept_paid.is_zero() {
+         CONTRIBUTIONS.remove(storage, (wallet.clone(), denom.clone()));
+      } else {
+         CONTRIBUTIONS.save(storage, (wallet.clone(), denom.clone()), &kept_paid)?;
+      }
+      refunds.push((Denom::Native(denom), paid - kept_paid));
+   }
+   for (contract_addr, paid) in cw20_contributions {
+      let kept_paid = multiply_ratio_ceil(paid, kept, of);
+      if kept_paid.is_zero() {
+         CW20_CONTRIBUTIONS.remove(storage, (wallet.clone(), contract_addr.clone()));
+      } else {
+         CW20_CONTRIBUTIONS.save(storage, (wallet.clone(), contract_addr.clone()), &kept_paid)?;
+      }
+      refunds.push((Denom::Cw20(contract_addr), paid - kept_paid));
+   }
+
+   let mut msgs: Vec<CosmosMsg> = Vec::new();
+   let mut native_refund: Vec<Coin> = Vec::new();
+   for (denom, refund) in refunds {
+      if refund.is_zero() {
+         continue;
+      }
+      let key = denom_str(&denom);
+      RAISED.update(storage, key.clone(), |raised| -> StdResult<_> {
+         Ok(raised.unwrap_or_default().saturating_sub(refund))
+      })?;
+      if let Some(reserve) = REFUND_RESERVE.may_load(storage, key.clone())? {
+         REFUND_RESERVE.save(storage, key, &reserve.saturating_sub(refund))?;
+      }
+
+      match denom {
+         Denom::Native(denom) => native_refund.push(Coin::new(refund.u128(), denom)),
+         Denom::Cw20(_) => msgs.push(transfer_msg(wallet, &denom, refund)),
+      }
+   }
+   if !native_refund.is_empty() {
+      msgs.insert(
+         0,
+         CosmosMsg::Bank(BankMsg::Send {
+            to_address: wallet.to_string(),
+            amount: native_refund,
+         }),
+      );
+   }
+   Ok(msgs)
+}
+
+fn is_oversubscribed(store: &dyn Storage, wallet: &Addr) -> StdResult<Option<SaleResult>> {
+   let settled = PRO_RATA_SETTLED.has(store, wallet.clone());
+   match SALE_RESULT.may_load(store)? {
+      Some(result) if result.allocated < result.sold && !settled => Ok(Some(result)),
+      _ => Ok(None),
+   }
+}
+
+fn scale_user_info(user_info: &mut UserInfo, result: &SaleResult) {
+   let bought_amount = user_info
+      .bought_amount
+      .multiply_ratio(result.allocated, result.sold);
+   user_info.total_amount -= user_info.bought_amount - bought_amount;
+   user_info.bought_amount = bought_amount;
+   for round_amount in user_info.round_amounts.iter_mut() {
+      round_amount.amount = round_amount
+         .amount
+         .multiply_ratio(result.allocated, result.sold);
+   }
+}
+
+//user info as it is after the pro-rata cut, even if the wallet did not settle yet
+pub fn pro_rata_user_info(
+   store: &dyn Storage,
+   wallet: &Addr,
+   mut user_info: UserInfo,
+) -> StdResult<UserInfo> {
+   if let Some(result) = is_oversubscribed(store, wallet)? {
+      scale_user_info(&mut user_info, &result);
+   }
+   Ok(user_info)
+}
+
+//applies the pro-rata cut to the wallet once and refunds the unallocated payment
+fn settle_pro_rata(
+   storage: &mut dyn Storage,
+   wallet: &Addr,
+   user_info: &mut UserInfo,
+) -> StdResult<Vec<CosmosMsg>> {
+   let result = match is_oversubscribed(storage, wallet)? {
+      Some(result) => result,
+      None => return Ok(Vec::new()),
+   };
+
+   PRO_RATA_SETTLED.save(storage, wallet.clone(), &true)?;
+   scale_user_info(user_info, &result);
+   refund_contributions(storage, wallet, result.allocated, result.sold)
+}
+
+//splits off the share of the position holding the given unreleased amount,
+//keeping the released part proportional so both positions vest alike
+fn split_user_info(user_info: &mut UserInfo, amount: Uint128) -> UserInfo {
+   let unreleased = user_info.total_amount - user_info.released_amount;
+   let released_amount = user_info.released_amount.multiply_ratio(amount, unreleased);
+   let total_amount = released_amount + amount;
+
+   let bought_amount = user_info
+      .bought_amount
+      .multiply_ratio(total_amount, user_info.total_amount);
+   let mut round_amounts: Vec<RoundAmount> = Vec::new();
+   for round_amount in user_info.round_amounts.iter_mut() {
+      let amount = round_amount
+         .amount
+         .multiply_ratio(total_amount, user_info.total_amount);
+      round_amount.amount -= amount;
+      round_amounts.push(RoundAmount {
+         round_id: round_amount.round_id,
+         amount,
+      });
+   }
+   user_info.round_amounts.retain(|x| !x.amount.is_zero());
+   round_amounts.retain(|x| !x.amount.is_zero());
+
+   let mut category_amounts: Vec<CategoryAmount> = Vec::new();
+   for category_amount in user_info.category_amounts.iter_mut() {
+      let amount = category_amount
+         .amount
+         .multiply_ratio(total_amount, user_info.total_amount);
+      category_amount.amount -= amount;
+      category_amounts.push(CategoryAmount {
+         category: category_amount.category.clone(),
+         amount,
+      });
+   }
+   user_info.category_amounts.retain(|x| !x.amount.is_zero());
+   category_amounts.retain(|x| !x.amount.is_zero());
+
+   let revocable_amount = user_info
+      .revocable_amount
+      .multiply_ratio(total_amount, user_info.total_amount);
+   let vested_amount = user_info
+      .vested_amount
+      .multiply_ratio(total_amount, user_info.total_amount);
+
+   user_info.total_amount -= total_amount;
+   user_info.released_amount -= released_amount;
+   user_info.bought_amount -= bought_amount;
+   user_info.revocable_amount -= revocable_amount;
+   user_info.vested_amount -= vested_amount;
+   UserInfo {
+      total_amount,
+      released_amount,
+      bought_amount,
+      round_amounts,
+      category_amounts,
+      revocable_amount,
+      vested_amount,
+      vest_param: user_info.vest_param.clone(),
+   }
+}
+
+fn merge_user_info(user_info: &mut UserInfo, other: UserInfo) {
+   user_info.total_amount += other.total_amount;
+   user_info.released_amount += other.released_amount;
+   user_info.bought_amount += other.bought_amount;
+   user_info.revocable_amount += other.revocable_amount;
+   user_info.vested_amount += other.vested_amount;
+   for other_round in other.round_amounts {
+      match user_info
+         .round_amounts
+         .iter_mut()
+         .find(|x| x.round_id == other_round.round_id)
+      {
+         Some(round_amount) => round_amount.amount += other_round.amount,
+         None => user_info.round_amounts.push(other_round),
+      }
+   }
+   for other_category in other.category_amounts {
+      add_category_amount(user_info, &other_category.category, other_category.amount);
+   }
+}
+
+fn add_category_amount(user_info: &mut UserInfo, category: &str, amount: Uint128) {
+   match user_info
+      .category_amounts
+      .iter_mut()
+      .find(|x| x.category == category)
+   {
+      Some(category_amount) => category_amount.amount += amount,
+      None => user_info.category_amounts.push(CategoryAmount {
+         category: category.to_string(),
+         amount,
+      }),
+   }
+}
+
+pub fn try_transferposition(
+   deps: DepsMut,
+   info: MessageInfo,
+   recipient: String,
+   amount: Option<Uint128>,
+) -> Result<Response, ContractError> {
+   if PAUSED.load(deps.storage)?.claims {
+      return Err(ContractError::Paused {});
+   }
+
+   //-----------refunds are no longer possible once the release started------
+   let config = CONFIG.load(deps.storage)?;
+   if config.start_time.is_zero() {
+      return Err(ContractError::ReleaseNotStarted {});
+   }
+
+   let recipient = deps.api.addr_validate(recipient.as_str())?;
+   if recipient == info.sender || position_owner(deps.as_ref(), &recipient)? != recipient {
+      return Err(ContractError::InvalidRecipient {});
+   }
+   if position_owner(deps.as_ref(), &info.sender)? != info.sender {
+      return Err(ContractError::Unauthorized {});
+   }
+
+   let mut user_info = USERS.load(deps.storage, info.sender.clone())?;
+   let mut msgs = settle_pro_rata(deps.storage, &info.sender, &mut user_info)?;
+   let unreleased = user_info.total_amount - user_info.released_amount;
+   let amount = amount.unwrap_or(unreleased);
+   if amount.is_zero() || amount > unreleased {
+      return Err(ContractError::InvalidAmount { unreleased });
+   }
+
+   let position = split_user_info(&mut user_info, amount);
+   match user_info.total_amount.is_zero() {
+      true => USERS.remove(deps.storage, info.sender.clone()),
+      false => USERS.save(deps.storage, info.sender.clone(), &user_info)?,
+   }
+
+   let mut recipient_info = match USERS.may_load(deps.storage, recipient.clone())? {
+      Some(mut recipient_info) => {
+         msgs.extend(settle_pro_rata(deps.storage, &recipient, &mut recipient_info)?);
+         recipient_info
+      }
+      None => {
+         //the position moved in is cut already, it must not be cut again
+         PRO_RATA_SETTLED.save(deps.storage, recipient.clone(), &true)?;
+         UserInfo {
+            total_amount: Uint128::zero(),
+            released_amount: Uint128::zero(),
+            bought_amount: Uint128::zero(),
+            round_amounts: Vec::new(),
+            category_amounts: Vec::new(),
+            revocable_amount: Uint128::zero(),
+            vested_amount: Uint128::zero(),
+            vest_param: position.vest_param.clone(),
+         }
+      }
+   };
+   if recipient_info.vest_param != position.vest_param {
+      return Err(ContractError::InvalidRecipient {});
+   }
+   merge_user_info(&mut recipient_info, position);
+   USERS.save(deps.storage, recipient.clone(), &recipient_info)?;
+   msgs.extend(mint_position(deps.storage, &recipient)?);
+
+   Ok(Response::new()
+      .add_messages(msgs)
+      .add_attribute("action", "Transfer position")
+      .add_attribute("recipient", recipient)
+      .add_attribute("amount", amount))
+}
+
+pub fn try_adduser(
+   deps: DepsMut,
+   env: Env,
+   info: MessageInfo,
+   whitelist: Option<WhitelistProof>,
+   min_aqua_out: Option<Uint128>,
+) -> Result<Response, ContractError> {
+   if info.funds.is_empty() {
+      return Err(ContractError::NeedFunds {});
+   }
+
+   let payments = info
+      .funds
+      .into_iter()
+      .map(|fund| (Denom::Native(fund.denom), fund.amount))
+      .collect();
+   try_purchase(deps, env, info.sender, payments, whitelist, min_aqua_out)
+}
+
+pub fn try_receive(
+   deps: DepsMut,
+   env: Env,
+   info: MessageInfo,
+   wrapper: Cw20ReceiveMsg,
+) -> Result<Response, ContractError> {
+   if wrapper.amount.is_zero() {
+      return Err(ContractError::NeedFunds {});
+   }
+
+   let buyer = deps.api.addr_validate(wrapper.sender.as_str())?;
+   match from_binary(&wrapper.msg)? {
+      Cw20HookMsg::Deposit {} => try_deposit(deps, info, wrapper.amount),
+      Cw20HookMsg::AddUser {
+         whitelist,
+         min_aqua_out,
+      } => try_purchase(
+         deps,
+         env,
+         buyer,
+         vec![(Denom::Cw20(info.sender), wrapper.amount)],
+         whitelist,
+         min_aqua_out,
+      ),
+   }
+}
+
+//escrow is funded by sending AQUA with the Deposit hook
+pub fn try_deposit(
+   deps: DepsMut,
+   info: MessageInfo,
+   amount: Uint128,
+) -> Result<Response, ContractError> {
+   let config = CONFIG.load(deps.storage)?;
+   if info.sender != config.token_addr {
+      return Err(ContractError::InvalidEscrowToken {});
+   }
+
+   let funded = FUNDED.load(deps.storage)? + amount;
+   FUNDED.save(deps.storage, &funded)?;
+
+   Ok(Response::new()
+      .add_attribute("action", "Deposit escrow")
+      .add_attribute("amount", amount)
+      .add_attribute("funded", funded))
+}
+
+pub fn try_setescrow(
+   deps: DepsMut,
+   info: MessageInfo,
+   enabled: bool,
+) -> Result<Response, ContractError> {
+   //-----------check owner--------------------------
+   let config = CONFIG.load(deps.storage).unwrap();
+   if config.owner != info.sender && config.treasury != info.sender {
+      return Err(ContractError::Unauthorized {});
+   }
+
+   ESCROW.save(deps.storage, &enabled)?;
+   Ok(Response::new().add_attribute("action", "Set escrow"))
+}
+
+pub fn try_setpauser(
+   deps: DepsMut,
+   info: MessageInfo,
+   pauser: Option<String>,
+) -> Result<Response, ContractError> {
+   //-----------check owner--------------------------
+   let config = CONFIG.load(deps.storage).unwrap();
+   if config.owner != info.sender && config.treasury != info.sender {
+      return Err(ContractError::Unauthorized {});
+   }
+
+   match pauser {
+      Some(pauser) => {
+         let pauser = deps.api.addr_validate(pauser.as_str())?;
+         PAUSER.save(deps.storage, &pauser)?;
+      }
+      None => PAUSER.remove(deps.storage),
+   }
+   Ok(Response::new().add_attribute("action", "Set pauser"))
+}
+
+pub fn try_setpause(
+   deps: DepsMut,
+   info: MessageInfo,
+   paused: PauseFlags,
+) -> Result<Response, ContractError> {
+   //-----------check owner or pauser--------------------------
+   let config = CONFIG.load(deps.storage).unwrap();
+   let pauser = PAUSER.may_load(deps.storage)?;
+   if config.owner != info.sender
+      && config.treasury != info.sender
+      && pauser != Some(info.sender.clone())
+   {
+      return Err(ContractError::Unauthorized {});
+   }
+
+   PAUSED.save(deps.storage, &paused)?;
+   Ok(Response::new()
+      .add_attribute("action", "Set pause")
+      .add_attribute("sales", paused.sales.to_string())
+      .add_attribute("claims", paused.claims.to_string())
+      .add_attribute("admin", paused.admin.to_string()))
+}
+
+pub fn try_withdrawescrow(
+   deps: DepsMut,
+   info: MessageInfo,
+   wallet: String,
+   amount: Uint128,
+) -> Result<Response, ContractError> {
+   //-----------check owner--------------------------
+   let config = CONFIG.load(deps.storage).unwrap();
+   if config.owner != info.sender && config.treasury != info.sender {
+      return Err(ContractError::Unauthorized {});
+   }
+
+   if PAUSED.load(deps.storage)?.admin {
+      return Err(ContractError::Paused {});
+   }
+
+   //--------only what is not owed to the holders while escrow is used-----
+   let funded = FUNDED.load(deps.storage)?;
+   let surplus = match ESCROW.load(deps.storage)? {
+      true => funded.saturating_sub(obligations(deps.storage)?),
+      false => funded,
+   };
+   if amount > surplus {
+      return Err(ContractError::ExceedEscrowSurplus { surplus });
+   }
+   FUNDED.save(deps.storage, &(funded - amount))?;
+
+   let recipient = deps.api.addr_validate(wallet.as_str())?;
+   let token_addr = deps.api.addr_validate(config.token_addr.as_str())?;
+   Ok(Response::new()
+      .add_message(transfer_msg(&recipient, &Denom::Cw20(token_addr), amount))
+      .add_attribute("action", "Withdraw escrow"))
+}
+
+//price with the active round once rounds are defined
+pub fn current_aqua_price(
+   store: &dyn Storage,
+   env: &Env,
+) -> Result<(Option<(u64, Round)>, u128), ContractError> {
+   let round = active_round(store, env)?;
+   let has_rounds = ROUNDS
+      .keys(store, None, None, Order::Ascending)
+      .next()
+      .is_some();
+   let aqua_price = match &round {
+      Some((_, round)) => round.aqua_price.u128(),
+      None if has_rounds => return Err(ContractError::NoActiveRound {}),
+      None => AQUA_PRICE,
+   };
+   Ok((round, aqua_price))
+}
+
+//token price and AQUA amount for each payment, every paid coin has to be supported
+pub fn quote_payments(
+   deps: Deps,
+   env: &Env,
+   payments: Vec<(Denom, Uint128)>,
+   aqua_price: u128,
+) -> Result<Vec<(Denom, Uint128, Uint128, Uint128)>, ContractError> {
+   let mut purchases: Vec<(Denom, Uint128, Uint128, Uint128)> = Vec::new();
+   for (denom, paid) in payments {
+      let (is_support, price, aqua_amount) =
+         get_aqua_amount(deps, env, &denom, paid, aqua_price)?;
+      if !is_support {
+         return Err(ContractError::NotSupportToken {});
+      }
+      purchases.push((denom, paid, price, aqua_amount));
+   }
+   Ok(purchases)
+}
+
+fn try_purchase(
+   deps: DepsMut,
+   env: Env,
+   buyer: Addr,
+   payments: Vec<(Denom, Uint128)>,
+   whitelist: Option<WhitelistProof>,
+   min_aqua_out: Option<Uint128>,
+) -> Result<Response, ContractError> {
+   if PAUSED.load(deps.storage)?.sales {
+      return Err(ContractError::Paused {});
+   }
+
+   let whitelist_cap = verify_whitelist(deps.storage, &buyer, whitelist)?;
+   //-----------new tokens must not go to the holder of a sold position-------
+   if position_owner(deps.as_ref(), &buyer)? != buyer {
+      return Err(ContractError::PositionTransferred {});
+   }
+   if is_sale_failed(deps.storage, &env)? {
+      return Err(ContractError::SaleFailed {});
+   }
+
+   let (round, aqua_price) = current_aqua_price(deps.storage, &env)?;
+   let mut purchases = quote_payments(deps.as_ref(), &env, payments, aqua_price)?;
+   let mut amount: Uint128 = purchases.iter().map(|(_, _, _, aqua_amount)| *aqua_amount).sum();
+
+   let sale_config = SALE_CONFIG.load(deps.storage)?;
+   if sale_status(&sale_config, &env) != SaleStatus::Open {
+      return Err(ContractError::SaleNotOpen {});
+   }
+
+   //-----------cut the contribution at the hard cap and refund the rest--------
+   let mut sold = SOLD.load(deps.storage)?;
+   let mut available = match sale_config.pro_rata {
+      true => None,
+      false => sale_config.hard_cap.map(|hard_cap| hard_cap.saturating_sub(sold)),
+   };
+   if let Some((round_id, Round { cap: Some(cap), .. })) = &round {
+      let round_sold = ROUND_SOLD
+         .may_load(deps.storage, *round_id)?
+         .unwrap_or_default();
+      let round_available = cap.saturating_sub(round_sold);
+      available = Some(available.map_or(round_available, |x| x.min(round_available)));
+   }
+
+   let mut msgs: Vec<CosmosMsg> = Vec::new();
+   if let Some(available) = available {
+      if available.is_zero() {
+         return Err(ContractError::HardCapReached {});
+      }
+      if amount > available {
+         for (denom, paid, _, aqua_amount) in purchases.iter_mut() {
+            let refund = *paid - multiply_ratio_ceil(*paid, available, amount);
+            *paid -= refund;
+            *aqua_amount = aqua_amount.multiply_ratio(available, amount);
+
+            if !refund.is_zero() {
+               msgs.push(transfer_msg(&buyer, denom, refund));
+            }
+         }
+         amount = available;
+      }
+   }
+
+   //-----------limits apply to what is credited after the cut-------------
+   if let Some(min_aqua_out) = min_aqua_out {
+      if amount < min_aqua_out {
+         return Err(ContractError::MinAquaOut {
+            aqua_amount: amount,
+         });
+      }
+   }
+   if amount < sale_config.min_purchase {
+      return Err(ContractError::BelowMinPurchase {
+         min_purchase: sale_config.min_purchase,
+      });
+   }
+
+   let mut user_info = USERS
+      .may_load(deps.storage, buyer.clone())?
+      .unwrap_or(UserInfo {
+         total_amount: Uint128::zero(),
+         released_amount: Uint128::zero(),
+         bought_amount: Uint128::zero(),
+         round_amounts: Vec::new(),
+         category_amounts: Vec::new(),
+         revocable_amount: Uint128::zero(),
+         vested_amount: Uint128::zero(),
+         vest_param: None,
+      });
+   if let Some(max_allocation) = sale_config.max_allocation {
+      if user_info.total_amount + amount > max_allocation {
+         return Err(ContractError::ExceedMaxAllocation {
+            remaining: max_allocation.saturating_sub(user_info.total_amount),
+         });
+      }
+   }
+   if let Some(cap) = whitelist_cap {
+      if user_info.total_amount + amount > cap {
+         return Err(ContractError::ExceedMaxAllocation {
+            remaining: cap.saturating_sub(user_info.total_amount),
+         });
+      }
+   }
+   user_info.total_amount += amount;
+   user_info.bought_amount += amount;
+   if let Some((round_id, _)) = &round {
+      match user_info
+         .round_amounts
+         .iter_mut()
+         .find(|x| x.round_id == *round_id)
+      {
+         Some(round_amount) => round_amount.amount += amount,
+         None => user_info.round_amounts.push(RoundAmount {
+            round_id: *round_id,
+            amount,
+         }),
+      }
+      ROUND_SOLD.update(deps.storage, *round_id, |round_sold| -> StdResult<_> {
+         Ok(round_sold.unwrap_or_default() + amount)
+      })?;
+   }
+
+   USERS.save(deps.storage, buyer.clone(), &user_info)?;
+   msgs.extend(mint_position(deps.storage, &buyer)?);
+   let mut total = TOTAL.load(deps.storage)?;
+   total += amount;
+   TOTAL.save(deps.storage, &total)?;
+
+   sold += amount;
+   SOLD.save(deps.storage, &sold)?;
+
+   let mut res = Response::new()
+      .add_messages(msgs)
+      .add_attribute("action", "Add  User info");
+   for (denom, paid, price, aqua_amount) in purchases {
+      add_contribution(deps.storage, &buyer, &denom, paid)?;
+      record_contribution(
+         deps.storage,
+         &env,
+         ContributionRecord {
+            id: 0,
+            wallet: buyer.clone(),
+            denom: denom_str(&denom),
+            paid,
+            price,
+            aqua_price: Uint128::new(aqua_price),
+            aqua_amount,
+            time: Uint128::zero(),
+            source: ContributionSource::Purchase,
+            category: None,
+         },
+      )?;
+      res = res
+         .add_attribute("denom", denom_str(&denom))
+         .add_attribute("paid", paid)
+         .add_attribute("aqua_amount", aqua_amount);
+   }
+   Ok(res)
+}
+pub fn try_adduser_byowner(
+   deps: DepsMut,
+   env: Env,
+   info: MessageInfo,
+   wallet: String,
+   amount: Uint128,
+   vest_param: Option<VestingSchedule>,
+   category: Option<String>,
+) -> Result<Response, ContractError> {
+   //-----------check owner--------------------------
+   let config = CONFIG.load(deps.storage).unwrap();
+   if config.owner != info.sender && config.treasury != info.sender {
+      return Err(ContractError::Unauthorized {});
+   }
+
+   if PAUSED.load(deps.storage)?.admin {
+      return Err(ContractError::Paused {});
+   }
+
+   let address = deps.api.addr_validate(wallet.as_str()).unwrap();
+   if position_owner(deps.as_ref(), &address)? != address {
+      return Err(ContractError::PositionTransferred {});
+   }
+   let mut user_info = USERS
+      .may_load(deps.storage, address.clone())?
+      .unwrap_or(UserInfo {
+         total_amount: Uint128::zero(),
+         released_amount: Uint128::zero(),
+         bought_amount: Uint128::zero(),
+         round_amounts: Vec::new(),
+         category_amounts: Vec::new(),
+         revocable_amount: Uint128::zero(),
+         vested_amount: Uint128::zero(),
+         vest_param: None,
+      });
+   user_info.total_amount += amount;
+   user_info.revocable_amount += amount;
+   if let Some(vest_param) = vest_param {
+      validate_schedule(&vest_param)?;
+      user_info.vest_param = Some(vest_param);
+   }
+
+   //-----------allocations in a category are bound by its cap-------------
+   if let Some(name) = &category {
+      let cap = match CATEGORIES.may_load(deps.storage, name.clone())? {
+         Some(category) => category.cap,
+         None => return Err(ContractError::InvalidCategory {}),
+      };
+      let allocated = CATEGORY_ALLOCATED
+         .may_load(deps.storage, name.clone())?
+         .unwrap_or_default();
+      if let Some(cap) = cap {
+         if allocated + amount > cap {
+            return Err(ContractError::CategoryCapExceeded {
+               remaining: cap.saturating_sub(allocated),
+            });
+         }
+      }
+      CATEGORY_ALLOCATED.save(deps.storage, name.clone(), &(allocated + amount))?;
+      add_category_amount(&mut user_info, name, amount);
+   }
+
+   USERS.save(deps.storage, address.clone(), &user_info)?;
+   let mint_msg = mint_position(deps.storage, &address)?;
+   let mut total = TOTAL.load(deps.storage)?;
+   total += amount;
+   TOTAL.save(deps.storage, &total)?;
+
+   record_contribution(
+      deps.storage,
+      &env,
+      ContributionRecord {
+         id: 0,
+         wallet: address,
+         denom: String::new(),
+         paid: Uint128::zero(),
+         price: Uint128::zero(),
+         aqua_price: Uint128::zero(),
+         aqua_amount: amount,
+         time: Uint128::zero(),
+         source: ContributionSource::Owner,
+         category,
+      },
+   )?;
+
+   Ok(Response::new()
+      .add_messages(mint_msg)
+      .add_attribute("action", "Add  User info"))
+}
+pub fn try_claimrefund(
+   deps: DepsMut,
+   env: Env,
+   info: MessageInfo,
+) -> Result<Response, ContractError> {
+   if PAUSED.load(deps.storage)?.claims {
+      return Err(ContractError::Paused {});
+   }
+
+   //-----------unallocated part of an oversubscribed sale----------
+   if !is_sale_failed(deps.storage, &env)? {
+      let mut user_info = USERS.load(deps.storage, info.sender.clone())?;
+      let msgs = settle_pro_rata(deps.storage, &info.sender, &mut user_info)?;
+      if msgs.is_empty() {
+         return Err(ContractError::RefundNotAvailable {});
+      }
+      USERS.save(deps.storage, info.sender.clone(), &user_info)?;
+
+      return Ok(Response::new()
+         .add_messages(msgs)
+         .add_attribute("action", "Claim refund"));
+   }
+
+   //-----------everything of a failed sale-------------
+   let msgs = refund_contributions(deps.storage, &info.sender, Uint128::zero(), Uint128::new(1))?;
+   if msgs.is_empty() {
+      return Err(ContractError::NoRefundableFunds {});
+   }
+
+   let mut user_info = USERS.load(deps.storage, info.sender.clone())?;
+   let bought_amount = user_info.bought_amount;
+   user_info.total_amount -= bought_amount;
+   user_info.bought_amount = Uint128::zero();
+   for round_amount in user_info.round_amounts.drain(..) {
+      let round_sold = ROUND_SOLD.load(deps.storage, round_amount.round_id)?;
+      ROUND_SOLD.save(
+         deps.storage,
+         round_amount.round_id,
+         &(round_sold - round_amount.amount),
+      )?;
+   }
+   USERS.save(deps.storage, info.sender.clone(), &user_info)?;
+
+   let total = TOTAL.load(deps.storage)?;
+   TOTAL.save(deps.storage, &(total - bought_amount))?;
+   let sold = SOLD.load(deps.storage)?;
+   SOLD.save(deps.storage, &(sold - bought_amount))?;
+
+   Ok(Response::new()
+      .add_messages(msgs)
+      .add_attribute("action", "Claim refund"))
+}
+
+pub fn try_finalizesale(
+   deps: DepsMut,
+   env: Env,
+   info: MessageInfo,
+) -> Result<Response, ContractError> {
+   let config = CONFIG.load(deps.storage)?;
+   if config.owner != info.sender && config.treasury != info.sender {
+      return Err(ContractError::Unauthorized {});
+   }
+
+   if PAUSED.load(deps.storage)?.admin {
+      return Err(ContractError::Paused {});
+   }
+
+   let sale_config = SALE_CONFIG.load(deps.storage)?;
+   if !sale_config.pro_rata {
+      return Err(ContractError::ProRataDisabled {});
+   }
+   if SALE_RESULT.may_load(deps.storage)?.is_some() {
+      return Err(ContractError::SaleAlreadyFinalized {});
+   }
+   if sale_status(&sale_config, &env) != SaleStatus::Closed {
+      return Err(ContractError::SaleNotClosed {});
+   }
+   if is_sale_failed(deps.storage, &env)? {
+      return Err(ContractError::SaleFailed {});
+   }
+
+   //-----------cut the sold amount to the hard cap, wallets settle on their own------
+   let sold = SOLD.load(deps.storage)?;
+   let allocated = sale_config
+      .hard_cap
+      .map_or(sold, |hard_cap| hard_cap.min(sold));
+   if allocated < sold {
+      let total = TOTAL.load(deps.storage)?;
+      TOTAL.save(deps.storage, &(total - (sold - allocated)))?;
+      SOLD.save(deps.storage, &allocated)?;
+
+      let round_sold: Vec<(u64, Uint128)> = ROUND_SOLD
+         .range(deps.storage, None, None, Order::Ascending)
+         .collect::<StdResult<_>>()?;
+      for (round_id, amount) in round_sold {
+         ROUND_SOLD.save(deps.storage, round_id, &amount.multiply_ratio(allocated, sold))?;
+      }
+
+      let raised: Vec<(String, Uint128)> = RAISED
+         .range(deps.storage, None, None, Order::Ascending)
+         .collect::<StdResult<_>>()?;
+      for (denom, amount) in raised {
+         let reserve = amount - amount.multiply_ratio(allocated, sold);
+         REFUND_RESERVE.save(deps.storage, denom, &reserve)?;
+      }
+   }
+
+   SALE_RESULT.save(deps.storage, &SaleResult { sold, allocated })?;
+   Ok(Response::new()
+      .add_attribute("action", "Finalize sale")
+      .add_attribute("sold", sold)
+      .add_attribute("allocated", allocated))
+}
+
+pub fn try_setconfig(
+   deps: DepsMut,
+   info: MessageInfo,
+   treasury: String,
+   token_addr: String,
+) -> Result<Response, ContractError> {
+   //-----------check owner--------------------------
+   let mut config = CONFIG.load(deps.storage).unwrap();
+   if info.sender != config.owner {
+      return Err(ContractError::Unauthorized {});
+   }
+
+   config.treasury = treasury;
+   config.token_addr = token_addr;
+
+   CONFIG.save(deps.storage, &config)?;
+   Ok(Response::new().add_attribute("action", "SetConfig"))
+}
+
+pub fn try_proposenewowner(
+   deps: DepsMut,
+   env: Env,
+   info: MessageInfo,
+   owner: String,
+   expires_at: Option<Uint128>,
+) -> Result<Response, ContractError> {
+   //-----------check owner--------------------------
+   let config = CONFIG.load(deps.storage).unwrap();
+   if info.sender != config.owner {
+      return Err(ContractError::Unauthorized {});
+   }
+
+   let now = Uint128::new(env.block.time.seconds() as u128);
+   if expires_at.is_some_and(|expires_at| expires_at <= now) {
+      return Err(ContractError::OwnershipProposalExpired {});
+   }
+
+   let owner = deps.api.addr_validate(owner.as_str())?;
+   OWNERSHIP_PROPOSAL.save(
+      deps.storage,
+      &OwnershipProposal {
+         owner: owner.clone(),
+         expires_at,
+      },
+   )?;
+   Ok(Response::new()
+      .add_attribute("action", "Propose new owner")
+      .add_attribute("owner", owner))
+}
+
+pub fn try_acceptownership(
+   deps: DepsMut,
+   env: Env,
+   info: MessageInfo,
+) -> Result<Response, ContractError> {
+   //-----------only the proposed owner, before the proposal expires-----------
+   let proposal = match OWNERSHIP_PROPOSAL.may_load(deps.storage)? {
+      Some(proposal) if proposal.owner == info.sender => proposal,
+      _ => return Err(ContractError::NoOwnershipProposal {}),
+   };
+   let now = Uint128::new(env.block.time.seconds() as u128);
+   if proposal.expires_at.is_some_and(|expires_at| expires_at <= now) {
+      return Err(ContractError::OwnershipProposalExpired {});
+   }
+
+   let mut config = CONFIG.load(deps.storage)?;
+   config.owner = proposal.owner;
+   CONFIG.save(deps.storage, &config)?;
+   OWNERSHIP_PROPOSAL.remove(deps.storage);
+   Ok(Response::new()
+      .add_attribute("action", "Accept ownership")
+      .add_attribute("owner", info.sender))
+}
+
+pub fn try_cancelownershipproposal(
+   deps: DepsMut,
+   info: MessageInfo,
+) -> Result<Response, ContractError> {
+   //-----------check owner--------------------------
+   let config = CONFIG.load(deps.storage).unwrap();
+   if info.sender != config.owner {
+      return Err(ContractError::Unauthorized {});
+   }
+
+   if OWNERSHIP_PROPOSAL.may_load(deps.storage)?.is_none() {
+      return Err(ContractError::NoOwnershipProposal {});
+   }
+   OWNERSHIP_PROPOSAL.remove(deps.storage);
+   Ok(Response::new().add_attribute("action", "Cancel ownership proposal"))
+}
+
+pub fn try_setprice(
+   deps: DepsMut,
+   info: MessageInfo,
+   denom: String,
+   price: Uint128,
+) -> Result<Response, ContractError> {
+   let config = CONFIG.load(deps.storage)?;
+   if config.owner != info.sender && config.treasury != info.sender {
+      return Err(ContractError::Unauthorized {});
+   }
+
+   if let Some(mut accepted_denom) = ACCEPTED_DENOMS.may_load(deps.storage, denom.clone())? {
+      accepted_denom.price = price;
+      ACCEPTED_DENOMS.save(deps.storage, denom, &accepted_denom)?;
+   } else {
+      let contract_addr = Addr::unchecked(denom);
+      let mut accepted_cw20 = ACCEPTED_CW20S
+         .may_load(deps.storage, contract_addr.clone())?
+         .ok_or(ContractError::NotSupportToken {})?;
+      accepted_cw20.price = price;
+      ACCEPTED_CW20S.save(deps.storage, contract_addr, &accepted_cw20)?;
+   }
+   Ok(Response::new().add_attribute("action", "SetPrice"))
+}
+
+pub fn try_setaccepteddenom(
+   deps: DepsMut,
+   info: MessageInfo,
+   accepted_denom: AcceptedDenom,
+) -> Result<Response, ContractError> {
+   let config = CONFIG.load(deps.storage)?;
+   if config.owner != info.sender && config.treasury != info.sender {
+      return Err(ContractError::Unauthorized {});
+   }
+
+   ACCEPTED_DENOMS.save(deps.storage, accepted_denom.denom.clone(), &accepted_denom)?;
+   Ok(Response::new()
+      .add_attribute("action", "Set accepted denom")
+      .add_attribute("denom", accepted_denom.denom))
+}
+
+pub fn try_removeaccepteddenom(
+   deps: DepsMut,
+   info: MessageInfo,
+   denom: String,
+) -> Result<Response, ContractError> {
+   let config = CONFIG.load(deps.storage)?;
+   if config.owner != info.sender && config.treasury != info.sender {
+      return Err(ContractError::Unauthorized {});
+   }
+
+   ACCEPTED_DENOMS.remove(deps.storage, denom.clone());
+   Ok(Response::new()
+      .add_attribute("action", "Remove accepted denom")
+      .add_attribute("denom", denom))
+}
+
+pub fn try_setoracle(
+   deps: DepsMut,
+   info: MessageInfo,
+   oracle: Option<OracleConfig>,
+) -> Result<Response, ContractError> {
+   let config = CONFIG.load(deps.storage)?;
+   if config.owner != info.sender && config.treasury != info.sender {
+      return Err(ContractError::Unauthorized {});
+   }
+
+   match oracle {
+      Some(mut oracle) => {
+         oracle.oracle_addr = deps.api.addr_validate(oracle.oracle_addr.as_str())?.to_string();
+         ORACLE.save(deps.storage, &oracle)?;
+      }
+      None => ORACLE.remove(deps.storage),
+   }
+   Ok(Response::new().add_attribute("action", "Set Oracle"))
+}
+
+pub fn try_setacceptedcw20(
+   deps: DepsMut,
+   info: MessageInfo,
+   mut accepted_cw20: AcceptedDenom,
+) -> Result<Response, ContractError> {
+   let config = CONFIG.load(deps.storage)?;
+   if config.owner != info.sender && config.treasury != info.sender {
+      return Err(ContractError::Unauthorized {});
+   }
+
+   let contract_addr = deps.api.addr_validate(accepted_cw20.denom.as_str())?;
+   accepted_cw20.denom = contract_addr.to_string();
+   ACCEPTED_CW20S.save(deps.storage, contract_addr, &accepted_cw20)?;
+   Ok(Response::new()
+      .add_attribute("action", "Set accepted cw20")
+      .add_attribute("contract_addr", accepted_cw20.denom))
+}
+
+pub fn try_removeacceptedcw20(
+   deps: DepsMut,
+   info: MessageInfo,
+   contract_addr: String,
+) -> Result<Response, ContractError> {
+   let config = CONFIG.load(deps.storage)?;
+   if config.owner != info.sender && config.treasury != info.sender {
+      return Err(ContractError::Unauthorized {});
+   }
+
+   let contract_addr = deps.api.addr_validate(contract_addr.as_str())?;
+   ACCEPTED_CW20S.remove(deps.storage, contract_addr.clone());
+   Ok(Response::new()
+      .add_attribute("action", "Remove accepted cw20")
+      .add_attribute("contract_addr", contract_addr))
+}
+
+pub fn try_withdraw(
+   deps: DepsMut,
+   env: Env,
+   info: MessageInfo,
+   wallet: String,
+) -> Result<Response, ContractError> {
+   //-----------check owner--------------------------
+   let config = CONFIG.load(deps.storage).unwrap();
+   if config.owner != info.sender && config.treasury != info.sender {
+      return Err(ContractError::Unauthorized {});
+   }
+
+   if PAUSED.load(deps.storage)?.admin {
+      return Err(ContractError::Paused {});
+   }
+
+   //--------keep the funds while they may be refunded----
+   let sale_config = SALE_CONFIG.load(deps.storage)?;
+   if SOLD.load(deps.storage)? < sale_config.soft_cap {
+      return Err(ContractError::SoftCapNotReached {});
+   }
+   if sale_config.pro_rata && SALE_RESULT.may_load(deps.storage)?.is_none() {
+      return Err(ContractError::SaleNotFinalized {});
+   }
+
    //--------get all native coins ----------------------
    let balance: AllBalanceResponse =
       deps
@@ -321,12 +2207,51 @@ pub fn try_withdraw(
             address: env.contract.address.to_string(),
          }))?;
 
-   let bank_native = BankMsg::Send {
-      to_address: wallet.clone(),
-      amount: balance.amount,
-   };
+   //--------keeping what is reserved for pro-rata refunds
+   let mut coins: Vec<Coin> = Vec::new();
+   for mut coin in balance.amount {
+      let reserve = REFUND_RESERVE
+         .may_load(deps.storage, coin.denom.clone())?
+         .unwrap_or_default();
+      coin.amount = coin.amount.saturating_sub(reserve);
+      if !coin.amount.is_zero() {
+         coins.push(coin);
+      }
+   }
+
+   //--------and all accepted cw20 tokens -------------
+   let recipient = deps.api.addr_validate(wallet.as_str())?;
+   let mut msgs: Vec<CosmosMsg> = Vec::new();
+   for contract_addr in ACCEPTED_CW20S.keys(deps.storage, None, None, Order::Ascending) {
+      let contract_addr = contract_addr?;
+      let token_balance: Cw20BalanceResponse = deps.querier.query_wasm_smart(
+         contract_addr.clone(),
+         &Cw20QueryMsg::Balance {
+            address: env.contract.address.to_string(),
+         },
+      )?;
+      let mut reserve = REFUND_RESERVE
+         .may_load(deps.storage, contract_addr.to_string())?
+         .unwrap_or_default();
+      if contract_addr == config.token_addr {
+         reserve += FUNDED.load(deps.storage)?;
+      }
+      let amount = token_balance.balance.saturating_sub(reserve);
+      if !amount.is_zero() {
+         msgs.push(transfer_msg(&recipient, &Denom::Cw20(contract_addr), amount));
+      }
+   }
+   if !coins.is_empty() {
+      msgs.insert(
+         0,
+         CosmosMsg::Bank(BankMsg::Send {
+            to_address: wallet,
+            amount: coins,
+         }),
+      );
+   }
 
    Ok(Response::new()
-      .add_message(CosmosMsg::Bank(bank_native))
+      .add_messages(msgs)
       .add_attribute("action", "transfer all coins"))
 }
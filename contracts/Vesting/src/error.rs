@@ -1,6 +1,5 @@
-use cosmwasm_std::StdError;
+use cosmwasm_std::{StdError, Uint128};
 use thiserror::Error;
-use cosmwasm_std::{Uint128};
 
 #[derive(Error, Debug)]
 pub enum ContractError {
@@ -10,6 +9,9 @@ pub enum ContractError {
     #[error("Unauthorized")]
     Unauthorized {},
 
+    #[error("Paused")]
+    Paused {},
+
     #[error("Should call with Funds")]
     NeedFunds {},
 
@@ -22,6 +24,120 @@ pub enum ContractError {
     #[error("There is no enough tokens")]
     NotEnoughBalance {},
 
+    #[error("Oracle price is too old")]
+    StalePrice {},
+
+    #[error("Oracle price deviates too much from the set price")]
+    PriceDeviation {},
+
+    #[error("Purchase would return {aqua_amount} AQUA, below the requested minimum")]
+    MinAquaOut { aqua_amount: Uint128 },
+
+    #[error("Hard cap reached")]
+    HardCapReached {},
+
+    #[error("Soft cap not reached")]
+    SoftCapNotReached {},
+
+    #[error("Invalid round")]
+    InvalidRound {},
+
+    #[error("There is no active round")]
+    NoActiveRound {},
+
+    #[error("Round already has sales")]
+    RoundHasSales {},
+
+    #[error("Round overlaps round {round_id}")]
+    RoundOverlap { round_id: u64 },
+
+    #[error("Invalid vesting schedule")]
+    InvalidSchedule {},
+
+    #[error("Invalid category")]
+    InvalidCategory {},
+
+    #[error("Category has allocations")]
+    CategoryHasAllocations {},
+
+    #[error("Category cap exceeded, {remaining} AQUA remaining")]
+    CategoryCapExceeded { remaining: Uint128 },
+
+    #[error("Wallet has no revocable allocation")]
+    NotRevocable {},
+
+    #[error("Invalid merkle root")]
+    InvalidMerkleRoot {},
+
+    #[error("Invalid merkle proof")]
+    InvalidProof {},
+
+    #[error("Wallet is not whitelisted")]
+    NotWhitelisted {},
+
+    #[error("Sale is not open")]
+    SaleNotOpen {},
+
+    #[error("Purchase is below the minimum amount {min_purchase}")]
+    BelowMinPurchase { min_purchase: Uint128 },
+
+    #[error("Purchase exceeds the wallet allocation, remaining {remaining}")]
+    ExceedMaxAllocation { remaining: Uint128 },
+
+    #[error("Sale failed to reach the soft cap")]
+    SaleFailed {},
+
+    #[error("Sale is not closed yet")]
+    SaleNotClosed {},
+
+    #[error("Pro-rata allocation is disabled")]
+    ProRataDisabled {},
+
+    #[error("Sale is already finalized")]
+    SaleAlreadyFinalized {},
+
+    #[error("Sale is not finalized")]
+    SaleNotFinalized {},
+
+    #[error("Release has not started")]
+    ReleaseNotStarted {},
+
+    #[error("Invalid amount, at most {unreleased} can be transferred")]
+    InvalidAmount { unreleased: Uint128 },
+
+    #[error("Invalid recipient")]
+    InvalidRecipient {},
+
+    #[error("Positions are not represented as NFTs")]
+    NftDisabled {},
+
+    #[error("Position of this wallet is owned by another wallet")]
+    PositionTransferred {},
+
+    #[error("Refund is not available")]
+    RefundNotAvailable {},
+
+    #[error("There is no funds to refund")]
+    NoRefundableFunds {},
+
+    #[error("Not covered: short {balance_shortfall} balance, {allowance_shortfall} allowance")]
+    Insolvent {
+        balance_shortfall: Uint128,
+        allowance_shortfall: Uint128,
+    },
+
+    #[error("Escrow can only be funded with the AQUA token")]
+    InvalidEscrowToken {},
+
+    #[error("Only {surplus} AQUA in escrow exceeds the outstanding obligations")]
+    ExceedEscrowSurplus { surplus: Uint128 },
+
+    #[error("No ownership proposal for this wallet")]
+    NoOwnershipProposal {},
+
+    #[error("Ownership proposal expired")]
+    OwnershipProposalExpired {},
+
     #[error("Not correct Milestone status : {aust_balance} {estimate_exchange_rate} {epoch_exchange_rate} {withdraw_amount} {release_amount}")]
     Testing{
         aust_balance: String,
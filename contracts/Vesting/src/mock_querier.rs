@@ -6,7 +6,9 @@ use cosmwasm_std::{
    from_binary, from_slice, to_binary, Coin, ContractResult, CustomQuery, Empty,
    OwnedDeps, Querier, QuerierResult, QueryRequest, SystemError, SystemResult, Uint128, WasmQuery,
 };
-use cw20::{BalanceResponse as Cw20BalanceResponse, Cw20QueryMsg};
+use cw20::{AllowanceResponse, BalanceResponse as Cw20BalanceResponse, Cw20QueryMsg, Expiration};
+use cw721::{Cw721QueryMsg, OwnerOfResponse};
+use Interface::oracle::{PriceResponse, QueryMsg as OracleQueryMsg};
 
 use std::collections::HashMap;
 use std::marker::PhantomData;
@@ -28,22 +30,55 @@ pub fn mock_dependencies(
 pub struct WasmMockQuerier<C: DeserializeOwned = Empty> {
    base: MockQuerier<C>,
    token_querier: TokenQuerier,
+   oracle_querier: OracleQuerier,
+   nft_querier: NftQuerier,
 }
 
 #[derive(Clone, Default)]
 pub struct TokenQuerier {
    // this lets us iterate over all pairs that match the first string
    balances: HashMap<String, HashMap<String, Uint128>>,
+   // allowance granted by an owner to a spender for each token contract
+   allowances: HashMap<String, HashMap<(String, String), Uint128>>,
 }
 
 impl TokenQuerier {
    pub fn new(balances: &[(&String, &[(&String, &Uint128)])]) -> Self {
       TokenQuerier {
          balances: balances_to_map(balances),
+         allowances: HashMap::new(),
       }
    }
 }
 
+#[derive(Clone, Default)]
+pub struct OracleQuerier {
+   // price and last update time for each denom
+   prices: HashMap<String, PriceResponse>,
+}
+
+impl OracleQuerier {
+   pub fn new(prices: &[(&str, Uint128, Uint128)]) -> Self {
+      let mut prices_map: HashMap<String, PriceResponse> = HashMap::new();
+      for (denom, price, last_updated) in prices.iter() {
+         prices_map.insert(
+            denom.to_string(),
+            PriceResponse {
+               price: *price,
+               last_updated: *last_updated,
+            },
+         );
+      }
+      OracleQuerier { prices: prices_map }
+   }
+}
+
+#[derive(Clone, Default)]
+pub struct NftQuerier {
+   // owner of each token id for each nft contract
+   owners: HashMap<(String, String), String>,
+}
+
 pub(crate) fn balances_to_map(
    balances: &[(&String, &[(&String, &Uint128)])],
 ) -> HashMap<String, HashMap<String, Uint128>> {
@@ -79,6 +114,32 @@ impl<C: CustomQuery + DeserializeOwned> WasmMockQuerier<C> {
    pub fn handle_query(&self, request: &QueryRequest<C>) -> QuerierResult {
       match &request {
          QueryRequest::Wasm(WasmQuery::Smart { contract_addr, msg }) => {
+            if let Ok(OracleQueryMsg::Price { denom }) = from_binary(msg) {
+               return match self.oracle_querier.prices.get(&denom) {
+                  Some(price) => SystemResult::Ok(ContractResult::Ok(to_binary(price).unwrap())),
+                  None => SystemResult::Err(SystemError::InvalidRequest {
+                     error: format!("No oracle price exists for {}", denom),
+                     request: msg.as_slice().into(),
+                  }),
+               };
+            }
+
+            if let Ok(Cw721QueryMsg::OwnerOf { token_id, .. }) = from_binary(msg) {
+               return match self.nft_querier.owners.get(&(contract_addr.clone(), token_id)) {
+                  Some(owner) => SystemResult::Ok(ContractResult::Ok(
+                     to_binary(&OwnerOfResponse {
+                        owner: owner.clone(),
+                        approvals: vec![],
+                     })
+                     .unwrap(),
+                  )),
+                  None => SystemResult::Err(SystemError::InvalidRequest {
+                     error: format!("No owner exists for the nft of {}", contract_addr),
+                     request: msg.as_slice().into(),
+                  }),
+               };
+            }
+
             match from_binary(msg).unwrap() {
                Cw20QueryMsg::Balance { address } => {
                   let balances: &HashMap<String, Uint128> =
@@ -111,6 +172,23 @@ impl<C: CustomQuery + DeserializeOwned> WasmMockQuerier<C> {
                      to_binary(&Cw20BalanceResponse { balance }).unwrap(),
                   ))
                }
+               Cw20QueryMsg::Allowance { owner, spender } => {
+                  let allowance = self
+                     .token_querier
+                     .allowances
+                     .get(contract_addr)
+                     .and_then(|allowances| allowances.get(&(owner, spender)))
+                     .copied()
+                     .unwrap_or_default();
+
+                  SystemResult::Ok(ContractResult::Ok(
+                     to_binary(&AllowanceResponse {
+                        allowance,
+                        expires: Expiration::Never {},
+                     })
+                     .unwrap(),
+                  ))
+               }
                _ => panic!("DO NOT ENTER HERE"),
             }
          }
@@ -124,6 +202,8 @@ impl WasmMockQuerier {
       WasmMockQuerier {
          base,
          token_querier: TokenQuerier::default(),
+         oracle_querier: OracleQuerier::default(),
+         nft_querier: NftQuerier::default(),
       }
    }
 
@@ -131,4 +211,31 @@ impl WasmMockQuerier {
    pub fn with_token_balances(&mut self, balances: &[(&String, &[(&String, &Uint128)])]) {
       self.token_querier = TokenQuerier::new(balances);
    }
+
+   // configure the allowances as (token, owner, spender, allowance)
+   pub fn with_token_allowances(&mut self, allowances: &[(&str, &str, &str, Uint128)]) {
+      for (token, owner, spender, allowance) in allowances.iter() {
+         self
+            .token_querier
+            .allowances
+            .entry(token.to_string())
+            .or_default()
+            .insert((owner.to_string(), spender.to_string()), *allowance);
+      }
+   }
+
+   // configure the nft owners as (nft contract, token id, owner)
+   pub fn with_nft_owners(&mut self, owners: &[(&str, &str, &str)]) {
+      for (nft_addr, token_id, owner) in owners.iter() {
+         self
+            .nft_querier
+            .owners
+            .insert((nft_addr.to_string(), token_id.to_string()), owner.to_string());
+      }
+   }
+
+   // configure the oracle prices as (denom, price, last_updated)
+   pub fn with_oracle_prices(&mut self, prices: &[(&str, Uint128, Uint128)]) {
+      self.oracle_querier = OracleQuerier::new(prices);
+   }
 }
\ No newline at end of file
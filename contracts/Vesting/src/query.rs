@@ -1,15 +1,30 @@
 #[cfg(not(feature = "library"))]
 use cosmwasm_std::entry_point;
 use cosmwasm_std::{
-   to_binary, Addr, AllBalanceResponse, BankQuery, Binary, Coin, Deps, Env, QueryRequest,
-   StdResult, Uint128, Uint64,
+   to_binary, Addr, AllBalanceResponse, BankQuery, Binary, Coin, Deps, Env, Order, QueryRequest,
+   StdError, StdResult, Uint128,
 };
 
-use cw20::{BalanceResponse as Cw20BalanceResponse, Cw20QueryMsg, TokenInfoResponse};
+use cw_storage_plus::Bound;
+
+use cw20::{
+   BalanceResponse as Cw20BalanceResponse, Cw20Coin, Cw20QueryMsg, Denom, TokenInfoResponse,
+};
+
+use crate::contract::{
+   active_round, calc_pending, current_aqua_price, obligations, position_owner, pro_rata_user_info,
+   quote_payments, sale_status, solvency, DEFAULT_LIMIT, MAX_LIMIT,
+};
+use crate::state::{
+   ledger, ACCEPTED_CW20S, ACCEPTED_DENOMS, CATEGORIES, CATEGORY_ALLOCATED, CONFIG, CONTRIBUTIONS,
+   CW20_CONTRIBUTIONS, ESCROW, FUNDED, MERKLE_ROOT, NFT_CONTRACT, ORACLE, OWNERSHIP_PROPOSAL,
+   PAUSED, PAUSER, ROUNDS, ROUND_SOLD, SALE_CONFIG, SALE_RESULT, SOLD, USERS,
+};
+use Interface::vesting::{
+   AcceptedDenom, AllocationInfo, CategoryInfo, Config, ContributionRecord, Contributions,
+   EscrowInfo, PauseInfo, QueryMsg, RoundInfo, SaleConfig, UserInfo,
+};
 
-use crate::contract::calc_pending;
-use crate::state::{CONFIG, JUNO_PRICE, TOTAL, USDC_PRICE, USERS, VEST_PARAM};
-use Interface::vesting::{Config, QueryMsg, UserInfo};
 
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
@@ -18,21 +33,82 @@ pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
 
       QueryMsg::GetConfig {} => to_binary(&query_getconfig(deps)?),
 
+      QueryMsg::GetOwnershipProposal {} => to_binary(&OWNERSHIP_PROPOSAL.may_load(deps.storage)?),
+
       QueryMsg::GetPendingTokens { wallet } => to_binary(&query_pendingtokens(deps, env, wallet)?),
 
       QueryMsg::GetUserInfo { wallet } => to_binary(&query_getuserinfo(deps, wallet)?),
 
       QueryMsg::GetAllInfo {} => to_binary(&query_allinfo(deps)?),
 
-      QueryMsg::GetPrice {} => {
-         let usdc_price = USDC_PRICE.load(deps.storage)?;
-         let juno_price = JUNO_PRICE.load(deps.storage)?;
-         to_binary(&vec![usdc_price, juno_price])
+      QueryMsg::GetPrice { denom } => {
+         let accepted_denom = match ACCEPTED_DENOMS.may_load(deps.storage, denom.clone())? {
+            Some(accepted_denom) => accepted_denom,
+            None => ACCEPTED_CW20S.load(deps.storage, Addr::unchecked(denom))?,
+         };
+         to_binary(&accepted_denom.price)
+      }
+
+      QueryMsg::GetAcceptedDenoms {} => to_binary(&query_accepteddenoms(deps)?),
+
+      QueryMsg::GetAcceptedCw20s {} => to_binary(&query_acceptedcw20s(deps)?),
+
+      QueryMsg::GetOracle {} => to_binary(&ORACLE.may_load(deps.storage)?),
+
+      QueryMsg::GetNftContract {} => to_binary(&NFT_CONTRACT.may_load(deps.storage)?),
+
+      QueryMsg::GetPositionOwner { wallet } => to_binary(&position_owner(deps, &wallet)?),
+
+      QueryMsg::SimulatePurchase { funds, cw20s } => {
+         to_binary(&query_simulatepurchase(deps, env, funds, cw20s)?)
       }
+
+      QueryMsg::GetSaleConfig {} => to_binary(&query_saleconfig(deps)?),
+
+      QueryMsg::GetSoldAmount {} => to_binary(&SOLD.load(deps.storage)?),
+
+      QueryMsg::GetContributions { wallet } => to_binary(&query_contributions(deps, wallet)?),
+
+      QueryMsg::GetAllocation { wallet } => to_binary(&query_allocation(deps, wallet)?),
+
+      QueryMsg::GetSaleStatus {} => {
+         let sale_config = SALE_CONFIG.load(deps.storage)?;
+         to_binary(&sale_status(&sale_config, &env))
+      }
+
+      QueryMsg::GetSaleResult {} => to_binary(&SALE_RESULT.may_load(deps.storage)?),
+
+      QueryMsg::GetMerkleRoot {} => to_binary(&MERKLE_ROOT.may_load(deps.storage)?),
+
+      QueryMsg::GetRounds {} => to_binary(&query_rounds(deps)?),
+
+      QueryMsg::GetActiveRound {} => to_binary(&query_activeround(deps, env)?),
+
+      QueryMsg::GetCategories {} => to_binary(&query_categories(deps)?),
+
+      QueryMsg::GetEscrow {} => to_binary(&EscrowInfo {
+         enabled: ESCROW.load(deps.storage)?,
+         funded: FUNDED.load(deps.storage)?,
+         obligations: obligations(deps.storage)?,
+      }),
+
+      QueryMsg::GetSolvency {} => to_binary(&solvency(deps, &env)?),
+
+      QueryMsg::GetPauseStatus {} => to_binary(&PauseInfo {
+         pauser: PAUSER.may_load(deps.storage)?,
+         paused: PAUSED.load(deps.storage)?,
+      }),
+
+      QueryMsg::GetUserHistory {
+         wallet,
+         start_after,
+         limit,
+      } => to_binary(&query_userhistory(deps, wallet, start_after, limit)?),
    }
 }
 fn query_pendingtokens(deps: Deps, env: Env, wallet: Addr) -> StdResult<Uint128> {
-   let user_info = USERS.load(deps.storage, wallet)?;
+   let user_info = USERS.load(deps.storage, wallet.clone())?;
+   let user_info = pro_rata_user_info(deps.storage, &wallet, user_info)?;
 
    let pending_amount = calc_pending(deps.storage, env.clone(), &user_info);
 
@@ -72,8 +148,8 @@ fn query_getconfig(deps: Deps) -> StdResult<Config> {
 }
 
 fn query_getuserinfo(deps: Deps, wallet: Addr) -> StdResult<UserInfo> {
-   let user = USERS.load(deps.storage, wallet)?;
-   Ok(user)
+   let user = USERS.load(deps.storage, wallet.clone())?;
+   pro_rata_user_info(deps.storage, &wallet, user)
 }
 
 fn query_allinfo(deps: Deps) -> StdResult<Vec<UserInfo>> {
@@ -84,7 +160,152 @@ fn query_allinfo(deps: Deps) -> StdResult<Vec<UserInfo>> {
 
    let mut all_userinfo: Vec<UserInfo> = Vec::new();
    for x in all {
-      all_userinfo.push(x.1);
+      all_userinfo.push(pro_rata_user_info(deps.storage, &x.0, x.1)?);
    }
    Ok(all_userinfo)
 }
+
+fn query_saleconfig(deps: Deps) -> StdResult<SaleConfig> {
+   let sale_config = SALE_CONFIG.load(deps.storage)?;
+   Ok(sale_config)
+}
+
+fn query_contributions(deps: Deps, wallet: Addr) -> StdResult<Contributions> {
+   let native = CONTRIBUTIONS
+      .prefix(wallet.clone())
+      .range(deps.storage, None, None, Order::Ascending)
+      .map(|item| item.map(|(denom, amount)| Coin::new(amount.u128(), denom)))
+      .collect::<StdResult<_>>()?;
+   let cw20 = CW20_CONTRIBUTIONS
+      .prefix(wallet)
+      .range(deps.storage, None, None, Order::Ascending)
+      .map(|item| {
+         item.map(|(address, amount)| Cw20Coin {
+            address: address.to_string(),
+            amount,
+         })
+      })
+      .collect::<StdResult<_>>()?;
+
+   Ok(Contributions { native, cw20 })
+}
+
+fn query_allocation(deps: Deps, wallet: Addr) -> StdResult<AllocationInfo> {
+   let sale_config = SALE_CONFIG.load(deps.storage)?;
+   let total_amount = USERS
+      .may_load(deps.storage, wallet)?
+      .map(|user| user.total_amount)
+      .unwrap_or_default();
+
+   Ok(AllocationInfo {
+      min_purchase: sale_config.min_purchase,
+      max_allocation: sale_config.max_allocation,
+      total_amount,
+      remaining: sale_config
+         .max_allocation
+         .map(|max_allocation| max_allocation.saturating_sub(total_amount)),
+   })
+}
+
+fn query_categories(deps: Deps) -> StdResult<Vec<CategoryInfo>> {
+   CATEGORIES
+      .range(deps.storage, None, None, Order::Ascending)
+      .map(|item| {
+         let (name, category) = item?;
+         let allocated = CATEGORY_ALLOCATED
+            .may_load(deps.storage, name.clone())?
+            .unwrap_or_default();
+         Ok(CategoryInfo {
+            name,
+            category,
+            allocated,
+         })
+      })
+      .collect()
+}
+
+fn query_rounds(deps: Deps) -> StdResult<Vec<RoundInfo>> {
+   ROUNDS
+      .range(deps.storage, None, None, Order::Ascending)
+      .map(|item| {
+         let (round_id, round) = item?;
+         let sold = ROUND_SOLD
+            .may_load(deps.storage, round_id)?
+            .unwrap_or_default();
+         Ok(RoundInfo {
+            round_id,
+            round,
+            sold,
+         })
+      })
+      .collect()
+}
+
+fn query_activeround(deps: Deps, env: Env) -> StdResult<Option<RoundInfo>> {
+   let round = active_round(deps.storage, &env)?;
+   round
+      .map(|(round_id, round)| {
+         let sold = ROUND_SOLD
+            .may_load(deps.storage, round_id)?
+            .unwrap_or_default();
+         Ok(RoundInfo {
+            round_id,
+            round,
+            sold,
+         })
+      })
+      .transpose()
+}
+
+fn query_accepteddenoms(deps: Deps) -> StdResult<Vec<AcceptedDenom>> {
+   ACCEPTED_DENOMS
+      .range(deps.storage, None, None, Order::Ascending)
+      .map(|item| item.map(|(_, accepted_denom)| accepted_denom))
+      .collect()
+}
+
+fn query_acceptedcw20s(deps: Deps) -> StdResult<Vec<AcceptedDenom>> {
+   ACCEPTED_CW20S
+      .range(deps.storage, None, None, Order::Ascending)
+      .map(|item| item.map(|(_, accepted_cw20)| accepted_cw20))
+      .collect()
+}
+
+fn query_simulatepurchase(
+   deps: Deps,
+   env: Env,
+   funds: Vec<Coin>,
+   cw20s: Vec<Cw20Coin>,
+) -> StdResult<Uint128> {
+   let mut payments: Vec<(Denom, Uint128)> = funds
+      .into_iter()
+      .map(|fund| (Denom::Native(fund.denom), fund.amount))
+      .collect();
+   for cw20 in cw20s {
+      let contract_addr = deps.api.addr_validate(cw20.address.as_str())?;
+      payments.push((Denom::Cw20(contract_addr), cw20.amount));
+   }
+
+   let quote = current_aqua_price(deps.storage, &env)
+      .and_then(|(_, aqua_price)| quote_payments(deps, &env, payments, aqua_price))
+      .map_err(|err| StdError::generic_err(err.to_string()))?;
+   Ok(quote.iter().map(|(_, _, _, aqua_amount)| *aqua_amount).sum())
+}
+
+fn query_userhistory(
+   deps: Deps,
+   wallet: Addr,
+   start_after: Option<u64>,
+   limit: Option<u32>,
+) -> StdResult<Vec<ContributionRecord>> {
+   let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+   let start = start_after.map(Bound::exclusive);
+   ledger()
+      .idx
+      .wallet
+      .prefix(wallet)
+      .range(deps.storage, start, None, Order::Ascending)
+      .take(limit)
+      .map(|item| item.map(|(_, record)| record))
+      .collect()
+}
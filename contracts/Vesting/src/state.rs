@@ -1,12 +1,64 @@
 use cosmwasm_std::{Addr, Uint128};
-use cw_storage_plus::{Item, Map};
-use Interface::vesting::{Config, UserInfo, VestingParameter};
+use cw_storage_plus::{Index, IndexList, IndexedMap, Item, Map, MultiIndex};
+use Interface::vesting::{
+    AcceptedDenom, Category, Config, ContributionRecord, OracleConfig, OwnershipProposal,
+    PauseFlags, Round, SaleConfig, SaleResult, UserInfo, VestingSchedule,
+};
 
 pub const CONFIG: Item<Config> = Item::new("config");
+pub const OWNERSHIP_PROPOSAL: Item<OwnershipProposal> = Item::new("ownership proposal");
 
-pub const VEST_PARAM: Item<VestingParameter> = Item::new("vesting param");
+pub const VEST_PARAM: Item<VestingSchedule> = Item::new("vesting param");
 pub const USERS: Map<Addr, UserInfo> = Map::new("users");
 pub const TOTAL: Item<Uint128> = Item::new("total");
+pub const RELEASED: Item<Uint128> = Item::new("released");
 
-pub const USDC_PRICE: Item<Uint128> = Item::new("usdc_price");
-pub const JUNO_PRICE: Item<Uint128> = Item::new("juno_price");
\ No newline at end of file
+pub const ESCROW: Item<bool> = Item::new("escrow");
+pub const FUNDED: Item<Uint128> = Item::new("funded");
+
+pub const PAUSED: Item<PauseFlags> = Item::new("paused");
+pub const PAUSER: Item<Addr> = Item::new("pauser");
+
+pub const ACCEPTED_DENOMS: Map<String, AcceptedDenom> = Map::new("accepted denoms");
+pub const ACCEPTED_CW20S: Map<Addr, AcceptedDenom> = Map::new("accepted cw20s");
+pub const ORACLE: Item<OracleConfig> = Item::new("oracle");
+
+pub const NFT_CONTRACT: Item<Addr> = Item::new("nft contract");
+pub const POSITION_NFTS: Map<Addr, Addr> = Map::new("position nfts");
+
+pub const SALE_CONFIG: Item<SaleConfig> = Item::new("sale config");
+pub const SOLD: Item<Uint128> = Item::new("sold");
+pub const CONTRIBUTIONS: Map<(Addr, String), Uint128> = Map::new("contributions");
+pub const CW20_CONTRIBUTIONS: Map<(Addr, Addr), Uint128> = Map::new("cw20 contributions");
+pub const RAISED: Map<String, Uint128> = Map::new("raised");
+
+pub const SALE_RESULT: Item<SaleResult> = Item::new("sale result");
+pub const REFUND_RESERVE: Map<String, Uint128> = Map::new("refund reserve");
+pub const PRO_RATA_SETTLED: Map<Addr, bool> = Map::new("pro rata settled");
+pub const MERKLE_ROOT: Item<String> = Item::new("merkle root");
+
+pub const ROUNDS: Map<u64, Round> = Map::new("rounds");
+pub const ROUND_SOLD: Map<u64, Uint128> = Map::new("round sold");
+
+pub const CATEGORIES: Map<String, Category> = Map::new("categories");
+pub const CATEGORY_ALLOCATED: Map<String, Uint128> = Map::new("category allocated");
+
+pub const LEDGER_COUNT: Item<u64> = Item::new("ledger count");
+
+pub struct LedgerIndexes<'a> {
+    pub wallet: MultiIndex<'a, Addr, ContributionRecord, u64>,
+}
+
+impl<'a> IndexList<ContributionRecord> for LedgerIndexes<'a> {
+    fn get_indexes(&'_ self) -> Box<dyn Iterator<Item = &'_ dyn Index<ContributionRecord>> + '_> {
+        let v: Vec<&dyn Index<ContributionRecord>> = vec![&self.wallet];
+        Box::new(v.into_iter())
+    }
+}
+
+pub fn ledger<'a>() -> IndexedMap<'a, u64, ContributionRecord, LedgerIndexes<'a>> {
+    let indexes = LedgerIndexes {
+        wallet: MultiIndex::new(|record| record.wallet.clone(), "ledger", "ledger__wallet"),
+    };
+    IndexedMap::new("ledger", indexes)
+}
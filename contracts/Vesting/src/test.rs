@@ -1,18 +1,33 @@
-use super::*;
 use cosmwasm_std::testing::{mock_env, mock_info, MOCK_CONTRACT_ADDR};
 use cosmwasm_std::{
-    from_binary, Addr, AllBalanceResponse, BalanceResponse, BankQuery, Coin, CosmosMsg, Uint128,
-    WasmMsg, Uint64,
+    from_binary, to_binary, Addr, BankMsg, Coin, CosmosMsg, DepsMut, OwnedDeps, Uint128, WasmMsg,
 };
 
-use crate::contract::{execute, instantiate};
+use crate::contract::{calc_unlocked, execute, instantiate};
 use crate::query::query;
+use crate::ContractError;
 use Interface::vesting::{
-    Config, ExecuteMsg, InstantiateMsg, QueryMsg, UserInfo, VestingParameter,
+    AcceptedDenom, AllocationInfo, Category, CategoryInfo, Checkpoint, Config, ContributionRecord,
+    ContributionSource, Contributions, Cw20HookMsg, EscrowInfo, ExecuteMsg, InstantiateMsg,
+    OracleConfig, OwnershipProposal, PauseFlags, PauseInfo, QueryMsg, Round, RoundInfo, SaleConfig,
+    SaleResult, SaleStatus, SolvencyInfo, UserInfo, VestingParameter, VestingSchedule,
+    WhitelistProof,
 };
 
 use crate::mock_querier::mock_dependencies;
-use cw20::Cw20ExecuteMsg;
+use cw20::{Cw20Coin, Cw20ExecuteMsg, Cw20ReceiveMsg};
+use cw721_base::{ExecuteMsg as Cw721BaseExecuteMsg, Extension, MintMsg};
+use sha2::Digest;
+
+const JUNO: &str = "ujunox";
+
+fn juno() -> AcceptedDenom {
+    AcceptedDenom {
+        denom: String::from(JUNO),
+        price: Uint128::new(5280),
+        decimals: 6,
+    }
+}
 
 #[test]
 fn workflow() {
@@ -21,7 +36,8 @@ fn workflow() {
     let msg = InstantiateMsg {
         admin: Some(String::from("admin")),
         token_addr: String::from("token"),
-        treasury: String::from("treasury")
+        treasury: String::from("treasury"),
+        accepted_denoms: vec![juno()],
     };
     //instantiate
     let info = mock_info("admin", &[]);
@@ -59,3 +75,2573 @@ fn workflow() {
     // let res: ProjectInfo = from_binary(&project_info).unwrap();
     // println!("Project Info {:?}", res);
 }
+
+fn setup_sale(deps: DepsMut) {
+    let msg = InstantiateMsg {
+        admin: Some(String::from("admin")),
+        token_addr: String::from("token"),
+        treasury: String::from("treasury"),
+        accepted_denoms: vec![juno()],
+    };
+    let info = mock_info("admin", &[]);
+    instantiate(deps, mock_env(), info, msg).unwrap();
+}
+
+//pro-rata sale of 10 000 AQUA closing in 100 seconds, oversubscribed by user and user2
+fn oversubscribe_sale(mut deps: DepsMut) {
+    let now = mock_env().block.time.seconds() as u128;
+    let msg = ExecuteMsg::SetSaleConfig {
+        sale_config: SaleConfig {
+            hard_cap: Some(Uint128::new(10_000)),
+            soft_cap: Uint128::zero(),
+            soft_cap_deadline: Uint128::zero(),
+            min_purchase: Uint128::zero(),
+            max_allocation: None,
+            sale_start: Uint128::zero(),
+            sale_end: Some(Uint128::new(now + 100)),
+            pro_rata: true,
+        },
+    };
+    execute(deps.branch(), mock_env(), mock_info("admin", &[]), msg).unwrap();
+
+    let msg = ExecuteMsg::AddUser {
+        whitelist: None,
+        min_aqua_out: None,
+    };
+    let info = mock_info("user", &[Coin::new(30, JUNO)]);
+    execute(deps.branch(), mock_env(), info, msg.clone()).unwrap();
+    let info = mock_info("user2", &[Coin::new(60, JUNO)]);
+    execute(deps, mock_env(), info, msg).unwrap();
+}
+
+#[test]
+fn purchase_and_claim() {
+    let mut deps = mock_dependencies(&[]);
+    setup_sale(deps.as_mut());
+
+    let info = mock_info("user", &[Coin::new(30, JUNO)]);
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        info,
+        ExecuteMsg::AddUser {
+            whitelist: None,
+            min_aqua_out: None,
+        },
+    )
+    .unwrap();
+
+    let msg = QueryMsg::GetUserInfo {
+        wallet: Addr::unchecked("user"),
+    };
+    let user_info: UserInfo = from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+    assert_eq!(user_info.total_amount, Uint128::new(5280));
+
+    //start release and claim the whole amount after the vesting period
+    let info = mock_info("admin", &[]);
+    let msg = ExecuteMsg::StartRelease {
+        start_time: Uint128::new(mock_env().block.time.seconds() as u128),
+        require_solvency: None,
+    };
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    deps.querier.with_token_balances(&[(
+        &String::from("token"),
+        &[(&String::from("treasury"), &Uint128::new(1_000_000))],
+    )]);
+    let mut env = mock_env();
+    env.block.time = env.block.time.plus_seconds(7_776_000);
+    let info = mock_info("user", &[]);
+    let res = execute(
+        deps.as_mut(),
+        env,
+        info,
+        ExecuteMsg::ClaimPendingTokens {
+            position: None,
+            recipient: None,
+            msg: None,
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages[0].msg,
+        CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: String::from("token"),
+            msg: to_binary(&Cw20ExecuteMsg::TransferFrom {
+                owner: String::from("treasury"),
+                recipient: String::from("user"),
+                amount: Uint128::new(5280),
+            })
+            .unwrap(),
+            funds: vec![],
+        })
+    );
+}
+
+#[test]
+fn hard_cap() {
+    let mut deps = mock_dependencies(&[]);
+    setup_sale(deps.as_mut());
+
+    let info = mock_info("admin", &[]);
+    let msg = ExecuteMsg::SetSaleConfig {
+        sale_config: SaleConfig {
+            hard_cap: Some(Uint128::new(10_000)),
+            soft_cap: Uint128::zero(),
+            soft_cap_deadline: Uint128::zero(),
+            min_purchase: Uint128::zero(),
+            max_allocation: None,
+            sale_start: Uint128::zero(),
+            sale_end: None,
+            pro_rata: false,
+        },
+    };
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    //30 ujunox buys 5280, so 60 ujunox exceeds the hard cap and the excess is refunded
+    let info = mock_info("user", &[Coin::new(30, JUNO)]);
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        info,
+        ExecuteMsg::AddUser {
+            whitelist: None,
+            min_aqua_out: None,
+        },
+    )
+    .unwrap();
+    let info = mock_info("user2", &[Coin::new(60, JUNO)]);
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        info,
+        ExecuteMsg::AddUser {
+            whitelist: None,
+            min_aqua_out: None,
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages[0].msg,
+        CosmosMsg::Bank(BankMsg::Send {
+            to_address: String::from("user2"),
+            amount: vec![Coin::new(33, JUNO)],
+        })
+    );
+
+    let msg = QueryMsg::GetSoldAmount {};
+    let sold: Uint128 = from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+    assert_eq!(sold, Uint128::new(10_000));
+
+    let msg = QueryMsg::GetContributions {
+        wallet: Addr::unchecked("user2"),
+    };
+    let paid: Contributions = from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+    assert_eq!(paid.native, vec![Coin::new(27, JUNO)]);
+
+    let info = mock_info("user3", &[Coin::new(30, JUNO)]);
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        info,
+        ExecuteMsg::AddUser {
+            whitelist: None,
+            min_aqua_out: None,
+        },
+    )
+    .unwrap_err();
+    assert!(matches!(err, ContractError::HardCapReached {}));
+}
+
+#[test]
+fn soft_cap_refund() {
+    let mut deps = mock_dependencies(&[]);
+    setup_sale(deps.as_mut());
+
+    let deadline = mock_env().block.time.seconds() + 100;
+    let info = mock_info("admin", &[]);
+    let msg = ExecuteMsg::SetSaleConfig {
+        sale_config: SaleConfig {
+            hard_cap: None,
+            soft_cap: Uint128::new(100_000),
+            soft_cap_deadline: Uint128::new(deadline as u128),
+            min_purchase: Uint128::zero(),
+            max_allocation: None,
+            sale_start: Uint128::zero(),
+            sale_end: None,
+            pro_rata: false,
+        },
+    };
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let info = mock_info("user", &[Coin::new(30, JUNO)]);
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        info.clone(),
+        ExecuteMsg::AddUser {
+            whitelist: None,
+            min_aqua_out: None,
+        },
+    )
+    .unwrap();
+
+    //no refunds and no release before the deadline
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        info.clone(),
+        ExecuteMsg::ClaimRefund {},
+    )
+    .unwrap_err();
+    assert!(matches!(err, ContractError::RefundNotAvailable {}));
+    let msg = ExecuteMsg::StartRelease {
+        start_time: Uint128::new(1),
+        require_solvency: None,
+    };
+    let err = execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), msg).unwrap_err();
+    assert!(matches!(err, ContractError::SoftCapNotReached {}));
+
+    let mut env = mock_env();
+    env.block.time = env.block.time.plus_seconds(101);
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        info.clone(),
+        ExecuteMsg::ClaimRefund {},
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages[0].msg,
+        CosmosMsg::Bank(BankMsg::Send {
+            to_address: String::from("user"),
+            amount: vec![Coin::new(30, JUNO)],
+        })
+    );
+
+    let msg = QueryMsg::GetUserInfo {
+        wallet: Addr::unchecked("user"),
+    };
+    let user_info: UserInfo =
+        from_binary(&query(deps.as_ref(), env.clone(), msg).unwrap()).unwrap();
+    assert_eq!(user_info.total_amount, Uint128::zero());
+
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        info.clone(),
+        ExecuteMsg::ClaimRefund {},
+    )
+    .unwrap_err();
+    assert!(matches!(err, ContractError::NoRefundableFunds {}));
+    let err = execute(
+        deps.as_mut(),
+        env,
+        info,
+        ExecuteMsg::AddUser {
+            whitelist: None,
+            min_aqua_out: None,
+        },
+    )
+    .unwrap_err();
+    assert!(matches!(err, ContractError::SaleFailed {}));
+}
+
+#[test]
+fn wallet_limits() {
+    let mut deps = mock_dependencies(&[]);
+    setup_sale(deps.as_mut());
+
+    let info = mock_info("admin", &[]);
+    let msg = ExecuteMsg::SetSaleConfig {
+        sale_config: SaleConfig {
+            hard_cap: None,
+            soft_cap: Uint128::zero(),
+            soft_cap_deadline: Uint128::zero(),
+            min_purchase: Uint128::new(1_000),
+            max_allocation: Some(Uint128::new(12_000)),
+            sale_start: Uint128::zero(),
+            sale_end: None,
+            pro_rata: false,
+        },
+    };
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let info = mock_info("user", &[Coin::new(5, JUNO)]);
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        info,
+        ExecuteMsg::AddUser {
+            whitelist: None,
+            min_aqua_out: None,
+        },
+    )
+    .unwrap_err();
+    assert!(matches!(err, ContractError::BelowMinPurchase { .. }));
+
+    let info = mock_info("user", &[Coin::new(30, JUNO)]);
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        info.clone(),
+        ExecuteMsg::AddUser {
+            whitelist: None,
+            min_aqua_out: None,
+        },
+    )
+    .unwrap();
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        info.clone(),
+        ExecuteMsg::AddUser {
+            whitelist: None,
+            min_aqua_out: None,
+        },
+    )
+    .unwrap();
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        info,
+        ExecuteMsg::AddUser {
+            whitelist: None,
+            min_aqua_out: None,
+        },
+    )
+    .unwrap_err();
+    assert!(matches!(
+        err,
+        ContractError::ExceedMaxAllocation { remaining } if remaining == Uint128::new(1_440)
+    ));
+
+    let msg = QueryMsg::GetAllocation {
+        wallet: Addr::unchecked("user"),
+    };
+    let allocation: AllocationInfo =
+        from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+    assert_eq!(allocation.total_amount, Uint128::new(10_560));
+    assert_eq!(allocation.remaining, Some(Uint128::new(1_440)));
+}
+
+#[test]
+fn sale_window() {
+    let mut deps = mock_dependencies(&[]);
+    setup_sale(deps.as_mut());
+
+    let now = mock_env().block.time.seconds() as u128;
+    let info = mock_info("admin", &[]);
+    let msg = ExecuteMsg::SetSaleConfig {
+        sale_config: SaleConfig {
+            hard_cap: None,
+            soft_cap: Uint128::zero(),
+            soft_cap_deadline: Uint128::zero(),
+            min_purchase: Uint128::zero(),
+            max_allocation: None,
+            sale_start: Uint128::new(now + 100),
+            sale_end: Some(Uint128::new(now + 200)),
+            pro_rata: false,
+        },
+    };
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let info = mock_info("user", &[Coin::new(30, JUNO)]);
+    for (offset, status) in [
+        (0, SaleStatus::Upcoming),
+        (150, SaleStatus::Open),
+        (250, SaleStatus::Closed),
+    ] {
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(offset);
+        let res = query(deps.as_ref(), env.clone(), QueryMsg::GetSaleStatus {}).unwrap();
+        assert_eq!(from_binary::<SaleStatus>(&res).unwrap(), status);
+
+        let res = execute(
+            deps.as_mut(),
+            env,
+            info.clone(),
+            ExecuteMsg::AddUser {
+                whitelist: None,
+                min_aqua_out: None,
+            },
+        );
+        match status {
+            SaleStatus::Open => assert!(res.is_ok()),
+            _ => assert!(matches!(res.unwrap_err(), ContractError::SaleNotOpen {})),
+        }
+    }
+}
+
+#[test]
+fn merkle_whitelist() {
+    let mut deps = mock_dependencies(&[]);
+    setup_sale(deps.as_mut());
+
+    let leaf1: [u8; 32] = sha2::Sha256::digest(b"user10000").into();
+    let leaf2: [u8; 32] = sha2::Sha256::digest(b"user2").into();
+    let mut leaves = [leaf1, leaf2];
+    leaves.sort_unstable();
+    let root = hex::encode(sha2::Sha256::digest(&leaves.concat()));
+
+    let info = mock_info("admin", &[]);
+    let msg = ExecuteMsg::RegisterMerkleRoot {
+        merkle_root: Some(String::from("1234")),
+    };
+    let err = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap_err();
+    assert!(matches!(err, ContractError::InvalidMerkleRoot {}));
+    let msg = ExecuteMsg::RegisterMerkleRoot {
+        merkle_root: Some(root),
+    };
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let info = mock_info("user", &[Coin::new(30, JUNO)]);
+    let msg = ExecuteMsg::AddUser {
+        whitelist: None,
+        min_aqua_out: None,
+    };
+    let err = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap_err();
+    assert!(matches!(err, ContractError::NotWhitelisted {}));
+
+    //a cap other than the one in the leaf does not verify
+    let msg = ExecuteMsg::AddUser {
+        whitelist: Some(WhitelistProof {
+            proof: vec![hex::encode(leaf2)],
+            cap: Some(Uint128::new(20_000)),
+        }),
+        min_aqua_out: None,
+    };
+    let err = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap_err();
+    assert!(matches!(err, ContractError::NotWhitelisted {}));
+
+    let msg = ExecuteMsg::AddUser {
+        whitelist: Some(WhitelistProof {
+            proof: vec![hex::encode(leaf2)],
+            cap: Some(Uint128::new(10_000)),
+        }),
+        min_aqua_out: None,
+    };
+    execute(deps.as_mut(), mock_env(), info.clone(), msg.clone()).unwrap();
+    let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+    assert!(matches!(err, ContractError::ExceedMaxAllocation { .. }));
+
+    let info = mock_info("user2", &[Coin::new(30, JUNO)]);
+    let msg = ExecuteMsg::AddUser {
+        whitelist: Some(WhitelistProof {
+            proof: vec![hex::encode(leaf1)],
+            cap: None,
+        }),
+        min_aqua_out: None,
+    };
+    execute(deps.as_mut(), mock_env(), info.clone(), msg.clone()).unwrap();
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+}
+
+#[test]
+fn sale_rounds() {
+    let mut deps = mock_dependencies(&[]);
+    setup_sale(deps.as_mut());
+
+    let now = mock_env().block.time.seconds() as u128;
+    let info = mock_info("admin", &[]);
+    let msg = ExecuteMsg::SetRound {
+        round_id: 1,
+        round: Round {
+            start_time: Uint128::new(now),
+            end_time: Uint128::new(now + 100),
+            aqua_price: Uint128::new(15),
+            cap: Some(Uint128::new(20_000)),
+            vest_param: Some(VestingSchedule::Linear {
+                period: Uint128::new(100),
+            }),
+        },
+    };
+    execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+    let msg = ExecuteMsg::SetRound {
+        round_id: 2,
+        round: Round {
+            start_time: Uint128::new(now + 200),
+            end_time: Uint128::new(now + 300),
+            aqua_price: Uint128::new(30),
+            cap: None,
+            vest_param: None,
+        },
+    };
+    execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+
+    let msg = ExecuteMsg::SetRound {
+        round_id: 3,
+        round: Round {
+            start_time: Uint128::new(now + 100),
+            end_time: Uint128::new(now + 150),
+            aqua_price: Uint128::new(20),
+            cap: None,
+            vest_param: None,
+        },
+    };
+    let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+    assert!(matches!(err, ContractError::RoundOverlap { round_id: 1 }));
+
+    //seed price is half the public one, the second purchase is cut at the round cap
+    let info = mock_info("user", &[Coin::new(30, JUNO)]);
+    let msg = ExecuteMsg::AddUser {
+        whitelist: None,
+        min_aqua_out: None,
+    };
+    execute(deps.as_mut(), mock_env(), info.clone(), msg.clone()).unwrap();
+    let res = execute(deps.as_mut(), mock_env(), info.clone(), msg.clone()).unwrap();
+    assert_eq!(
+        res.messages[0].msg,
+        CosmosMsg::Bank(BankMsg::Send {
+            to_address: String::from("user"),
+            amount: vec![Coin::new(3, JUNO)],
+        })
+    );
+
+    let mut env = mock_env();
+    env.block.time = env.block.time.plus_seconds(150);
+    let err = execute(deps.as_mut(), env.clone(), info.clone(), msg.clone()).unwrap_err();
+    assert!(matches!(err, ContractError::NoActiveRound {}));
+
+    env.block.time = env.block.time.plus_seconds(100);
+    execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+    let res = query(deps.as_ref(), env.clone(), QueryMsg::GetRounds {}).unwrap();
+    let rounds: Vec<RoundInfo> = from_binary(&res).unwrap();
+    assert_eq!(rounds[0].sold, Uint128::new(20_000));
+    assert_eq!(rounds[1].sold, Uint128::new(5_280));
+    let res = query(deps.as_ref(), env.clone(), QueryMsg::GetActiveRound {}).unwrap();
+    let active: Option<RoundInfo> = from_binary(&res).unwrap();
+    assert_eq!(active.unwrap().round_id, 2);
+
+    let err = execute(
+        deps.as_mut(),
+        env,
+        mock_info("admin", &[]),
+        ExecuteMsg::RemoveRound { round_id: 1 },
+    )
+    .unwrap_err();
+    assert!(matches!(err, ContractError::RoundHasSales {}));
+    let msg = ExecuteMsg::SetRound {
+        round_id: 1,
+        round: Round {
+            start_time: Uint128::new(now),
+            end_time: Uint128::new(now + 100),
+            aqua_price: Uint128::new(15),
+            cap: Some(Uint128::new(20_000)),
+            vest_param: None,
+        },
+    };
+    let err = execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), msg).unwrap_err();
+    assert!(matches!(err, ContractError::RoundHasSales {}));
+
+    //the seed allocation vests over its own period
+    let msg = ExecuteMsg::StartRelease {
+        start_time: Uint128::new(now + 400),
+        require_solvency: None,
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), msg).unwrap();
+    let mut env = mock_env();
+    env.block.time = env.block.time.plus_seconds(500);
+    let msg = QueryMsg::GetPendingTokens {
+        wallet: Addr::unchecked("user"),
+    };
+    let pending: Uint128 = from_binary(&query(deps.as_ref(), env, msg).unwrap()).unwrap();
+    assert_eq!(pending, Uint128::new(20_000));
+}
+
+#[test]
+fn accepted_denoms() {
+    let mut deps = mock_dependencies(&[]);
+    setup_sale(deps.as_mut());
+
+    let info = mock_info("admin", &[]);
+    let msg = ExecuteMsg::SetAcceptedDenom {
+        accepted_denom: AcceptedDenom {
+            denom: String::from("aevmos"),
+            price: Uint128::new(3000),
+            decimals: 18,
+        },
+    };
+    execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+    let msg = ExecuteMsg::SetPrice {
+        denom: String::from("aevmos"),
+        price: Uint128::new(1500),
+    };
+    execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+    let msg = ExecuteMsg::SetPrice {
+        denom: String::from("uatom"),
+        price: Uint128::new(1500),
+    };
+    let err = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap_err();
+    assert!(matches!(err, ContractError::NotSupportToken {}));
+
+    let msg = QueryMsg::GetPrice {
+        denom: String::from("aevmos"),
+    };
+    let price: Uint128 = from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+    assert_eq!(price, Uint128::new(1500));
+
+    //1 evmos at 1500 buys 50 AQUA at 30
+    let user = mock_info("user", &[Coin::new(1_000_000_000_000_000_000, "aevmos")]);
+    let msg = ExecuteMsg::AddUser {
+        whitelist: None,
+        min_aqua_out: None,
+    };
+    execute(deps.as_mut(), mock_env(), user.clone(), msg.clone()).unwrap();
+    let msg = QueryMsg::GetUserInfo {
+        wallet: Addr::unchecked("user"),
+    };
+    let user_info: UserInfo = from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+    assert_eq!(user_info.total_amount, Uint128::new(50_000_000));
+
+    let msg = ExecuteMsg::RemoveAcceptedDenom {
+        denom: String::from("aevmos"),
+    };
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+    let msg = ExecuteMsg::AddUser {
+        whitelist: None,
+        min_aqua_out: None,
+    };
+    let err = execute(deps.as_mut(), mock_env(), user, msg).unwrap_err();
+    assert!(matches!(err, ContractError::NotSupportToken {}));
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::GetAcceptedDenoms {}).unwrap();
+    assert_eq!(
+        from_binary::<Vec<AcceptedDenom>>(&res).unwrap(),
+        vec![juno()]
+    );
+}
+
+#[test]
+fn cw20_payment() {
+    let mut deps = mock_dependencies(&[]);
+    setup_sale(deps.as_mut());
+
+    let deadline = mock_env().block.time.seconds() + 100;
+    let info = mock_info("admin", &[]);
+    let msg = ExecuteMsg::SetAcceptedCw20 {
+        accepted_cw20: AcceptedDenom {
+            denom: String::from("usdc"),
+            price: Uint128::new(1000),
+            decimals: 6,
+        },
+    };
+    execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+    let msg = ExecuteMsg::SetSaleConfig {
+        sale_config: SaleConfig {
+            hard_cap: None,
+            soft_cap: Uint128::new(100_000),
+            soft_cap_deadline: Uint128::new(deadline as u128),
+            min_purchase: Uint128::zero(),
+            max_allocation: None,
+            sale_start: Uint128::zero(),
+            sale_end: None,
+            pro_rata: false,
+        },
+    };
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: String::from("user"),
+        amount: Uint128::new(30),
+        msg: to_binary(&Cw20HookMsg::AddUser {
+            whitelist: None,
+            min_aqua_out: None,
+        })
+        .unwrap(),
+    });
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("fake", &[]),
+        msg.clone(),
+    )
+    .unwrap_err();
+    assert!(matches!(err, ContractError::NotSupportToken {}));
+    execute(deps.as_mut(), mock_env(), mock_info("usdc", &[]), msg).unwrap();
+
+    let msg = QueryMsg::GetUserInfo {
+        wallet: Addr::unchecked("user"),
+    };
+    let user_info: UserInfo = from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+    assert_eq!(user_info.total_amount, Uint128::new(1000));
+
+    let msg = QueryMsg::GetContributions {
+        wallet: Addr::unchecked("user"),
+    };
+    let paid: Contributions = from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+    assert_eq!(
+        paid.cw20,
+        vec![Cw20Coin {
+            address: String::from("usdc"),
+            amount: Uint128::new(30),
+        }]
+    );
+
+    //the soft cap is missed, so the cw20 payment is sent back
+    let mut env = mock_env();
+    env.block.time = env.block.time.plus_seconds(101);
+    let info = mock_info("user", &[]);
+    let res = execute(deps.as_mut(), env, info, ExecuteMsg::ClaimRefund {}).unwrap();
+    assert_eq!(
+        res.messages[0].msg,
+        CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: String::from("usdc"),
+            msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                recipient: String::from("user"),
+                amount: Uint128::new(30),
+            })
+            .unwrap(),
+            funds: vec![],
+        })
+    );
+}
+
+#[test]
+fn multi_coin_purchase() {
+    let mut deps = mock_dependencies(&[]);
+    setup_sale(deps.as_mut());
+
+    let msg = ExecuteMsg::SetAcceptedDenom {
+        accepted_denom: AcceptedDenom {
+            denom: String::from("uusdc"),
+            price: Uint128::new(1000),
+            decimals: 6,
+        },
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), msg).unwrap();
+
+    let info = mock_info(
+        "user",
+        &[
+            Coin::new(30, JUNO),
+            Coin::new(30, "uusdc"),
+            Coin::new(30, "uatom"),
+        ],
+    );
+    let msg = ExecuteMsg::AddUser {
+        whitelist: None,
+        min_aqua_out: None,
+    };
+    let err = execute(deps.as_mut(), mock_env(), info, msg.clone()).unwrap_err();
+    assert!(matches!(err, ContractError::NotSupportToken {}));
+
+    let info = mock_info("user", &[Coin::new(30, JUNO), Coin::new(30, "uusdc")]);
+    let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+    let attributes: Vec<(&str, &str)> = res
+        .attributes
+        .iter()
+        .map(|attr| (attr.key.as_str(), attr.value.as_str()))
+        .collect();
+    assert_eq!(
+        attributes[1..],
+        [
+            ("denom", JUNO),
+            ("paid", "30"),
+            ("aqua_amount", "5280"),
+            ("denom", "uusdc"),
+            ("paid", "30"),
+            ("aqua_amount", "1000"),
+        ]
+    );
+
+    let msg = QueryMsg::GetUserInfo {
+        wallet: Addr::unchecked("user"),
+    };
+    let user_info: UserInfo = from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+    assert_eq!(user_info.total_amount, Uint128::new(6280));
+}
+
+#[test]
+fn oracle_price() {
+    let mut deps = mock_dependencies(&[]);
+    setup_sale(deps.as_mut());
+
+    let now = mock_env().block.time.seconds() as u128;
+    let msg = ExecuteMsg::SetOracle {
+        oracle: Some(OracleConfig {
+            oracle_addr: String::from("oracle"),
+            max_age: Uint128::new(60),
+            max_deviation: Uint128::new(10),
+        }),
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), msg).unwrap();
+
+    let info = mock_info("user", &[Coin::new(30, JUNO)]);
+    let msg = ExecuteMsg::AddUser {
+        whitelist: None,
+        min_aqua_out: None,
+    };
+    deps.querier
+        .with_oracle_prices(&[(JUNO, Uint128::new(5000), Uint128::new(now - 61))]);
+    let err = execute(deps.as_mut(), mock_env(), info.clone(), msg.clone()).unwrap_err();
+    assert!(matches!(err, ContractError::StalePrice {}));
+
+    deps.querier
+        .with_oracle_prices(&[(JUNO, Uint128::new(4000), Uint128::new(now - 10))]);
+    let err = execute(deps.as_mut(), mock_env(), info.clone(), msg.clone()).unwrap_err();
+    assert!(matches!(err, ContractError::PriceDeviation {}));
+
+    //the oracle price is used once it is fresh and close to the set price
+    deps.querier
+        .with_oracle_prices(&[(JUNO, Uint128::new(5000), Uint128::new(now - 10))]);
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let msg = QueryMsg::GetUserInfo {
+        wallet: Addr::unchecked("user"),
+    };
+    let user_info: UserInfo = from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+    assert_eq!(user_info.total_amount, Uint128::new(5000));
+}
+
+#[test]
+fn slippage_protection() {
+    let mut deps = mock_dependencies(&[]);
+    setup_sale(deps.as_mut());
+
+    let msg = QueryMsg::SimulatePurchase {
+        funds: vec![Coin::new(30, JUNO)],
+        cw20s: vec![],
+    };
+    let quote: Uint128 = from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+    assert_eq!(quote, Uint128::new(5280));
+
+    //the price drops between quoting and buying
+    let msg = ExecuteMsg::SetPrice {
+        denom: String::from(JUNO),
+        price: Uint128::new(5000),
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), msg).unwrap();
+
+    let info = mock_info("user", &[Coin::new(30, JUNO)]);
+    let msg = ExecuteMsg::AddUser {
+        whitelist: None,
+        min_aqua_out: Some(quote),
+    };
+    let err = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap_err();
+    assert!(matches!(
+        err,
+        ContractError::MinAquaOut { aqua_amount } if aqua_amount == Uint128::new(5000)
+    ));
+
+    let msg = ExecuteMsg::AddUser {
+        whitelist: None,
+        min_aqua_out: Some(Uint128::new(4900)),
+    };
+    execute(deps.as_mut(), mock_env(), info.clone(), msg.clone()).unwrap();
+
+    //the minimums hold for what is left under the hard cap
+    let sale_config = |min_purchase: u128| ExecuteMsg::SetSaleConfig {
+        sale_config: SaleConfig {
+            hard_cap: Some(Uint128::new(6000)),
+            soft_cap: Uint128::zero(),
+            soft_cap_deadline: Uint128::zero(),
+            min_purchase: Uint128::new(min_purchase),
+            max_allocation: None,
+            sale_start: Uint128::zero(),
+            sale_end: None,
+            pro_rata: false,
+        },
+    };
+    let admin = mock_info("admin", &[]);
+    execute(deps.as_mut(), mock_env(), admin.clone(), sale_config(0)).unwrap();
+    let err = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap_err();
+    assert!(matches!(
+        err,
+        ContractError::MinAquaOut { aqua_amount } if aqua_amount == Uint128::new(1000)
+    ));
+
+    execute(deps.as_mut(), mock_env(), admin, sale_config(2000)).unwrap();
+    let msg = ExecuteMsg::AddUser {
+        whitelist: None,
+        min_aqua_out: None,
+    };
+    let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+    assert!(matches!(err, ContractError::BelowMinPurchase { .. }));
+}
+
+#[test]
+fn pro_rata_allocation() {
+    let mut deps = mock_dependencies(&[Coin::new(90, JUNO)]);
+    setup_sale(deps.as_mut());
+
+    let now = mock_env().block.time.seconds() as u128;
+    let msg = ExecuteMsg::SetSaleConfig {
+        sale_config: SaleConfig {
+            hard_cap: Some(Uint128::new(10_000)),
+            soft_cap: Uint128::zero(),
+            soft_cap_deadline: Uint128::zero(),
+            min_purchase: Uint128::zero(),
+            max_allocation: None,
+            sale_start: Uint128::zero(),
+            sale_end: Some(Uint128::new(now + 100)),
+            pro_rata: true,
+        },
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), msg).unwrap();
+
+    //contributions over the hard cap are all accepted
+    let msg = ExecuteMsg::AddUser {
+        whitelist: None,
+        min_aqua_out: None,
+    };
+    let info = mock_info("user", &[Coin::new(30, JUNO)]);
+    execute(deps.as_mut(), mock_env(), info, msg.clone()).unwrap();
+    let info = mock_info("user2", &[Coin::new(60, JUNO)]);
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let admin = mock_info("admin", &[]);
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        admin.clone(),
+        ExecuteMsg::FinalizeSale {},
+    )
+    .unwrap_err();
+    assert!(matches!(err, ContractError::SaleNotClosed {}));
+    let msg = ExecuteMsg::StartRelease {
+        start_time: Uint128::new(now + 200),
+        require_solvency: None,
+    };
+    let err = execute(deps.as_mut(), mock_env(), admin.clone(), msg.clone()).unwrap_err();
+    assert!(matches!(err, ContractError::SaleNotFinalized {}));
+
+    //no refund reserve is kept before the sale is finalized
+    let withdraw = ExecuteMsg::Withdraw {
+        wallet: String::from("treasury"),
+    };
+    let err = execute(deps.as_mut(), mock_env(), admin.clone(), withdraw).unwrap_err();
+    assert!(matches!(err, ContractError::SaleNotFinalized {}));
+
+    let mut env = mock_env();
+    env.block.time = env.block.time.plus_seconds(101);
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        admin.clone(),
+        ExecuteMsg::FinalizeSale {},
+    )
+    .unwrap();
+    execute(deps.as_mut(), env.clone(), admin.clone(), msg).unwrap();
+
+    let res = query(deps.as_ref(), env.clone(), QueryMsg::GetSaleResult {}).unwrap();
+    assert_eq!(
+        from_binary::<Option<SaleResult>>(&res).unwrap(),
+        Some(SaleResult {
+            sold: Uint128::new(15_840),
+            allocated: Uint128::new(10_000),
+        })
+    );
+    let msg = QueryMsg::GetUserInfo {
+        wallet: Addr::unchecked("user"),
+    };
+    let user_info: UserInfo =
+        from_binary(&query(deps.as_ref(), env.clone(), msg).unwrap()).unwrap();
+    assert_eq!(user_info.total_amount, Uint128::new(3_333));
+
+    //the unallocated part of the payment is refunded once
+    let info = mock_info("user", &[]);
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        info.clone(),
+        ExecuteMsg::ClaimRefund {},
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages[0].msg,
+        CosmosMsg::Bank(BankMsg::Send {
+            to_address: String::from("user"),
+            amount: vec![Coin::new(11, JUNO)],
+        })
+    );
+    let err = execute(deps.as_mut(), env.clone(), info, ExecuteMsg::ClaimRefund {}).unwrap_err();
+    assert!(matches!(err, ContractError::RefundNotAvailable {}));
+
+    //the refunds still owed to user2 stay in the contract
+    let msg = ExecuteMsg::Withdraw {
+        wallet: String::from("treasury"),
+    };
+    let res = execute(deps.as_mut(), env.clone(), admin, msg).unwrap();
+    assert_eq!(
+        res.messages[0].msg,
+        CosmosMsg::Bank(BankMsg::Send {
+            to_address: String::from("treasury"),
+            amount: vec![Coin::new(67, JUNO)],
+        })
+    );
+
+    //claiming settles the pro-rata cut as well
+    deps.querier.with_token_balances(&[(
+        &String::from("token"),
+        &[(&String::from("treasury"), &Uint128::new(1_000_000))],
+    )]);
+    env.block.time = env.block.time.plus_seconds(7_776_100);
+    let info = mock_info("user2", &[]);
+    let res = execute(
+        deps.as_mut(),
+        env,
+        info,
+        ExecuteMsg::ClaimPendingTokens {
+            position: None,
+            recipient: None,
+            msg: None,
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages[0].msg,
+        CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: String::from("token"),
+            msg: to_binary(&Cw20ExecuteMsg::TransferFrom {
+                owner: String::from("treasury"),
+                recipient: String::from("user2"),
+                amount: Uint128::new(6_666),
+            })
+            .unwrap(),
+            funds: vec![],
+        })
+    );
+    assert_eq!(
+        res.messages[1].msg,
+        CosmosMsg::Bank(BankMsg::Send {
+            to_address: String::from("user2"),
+            amount: vec![Coin::new(22, JUNO)],
+        })
+    );
+}
+
+#[test]
+fn pro_rata_refund_rounding() {
+    let mut deps = mock_dependencies(&[Coin::new(3, JUNO)]);
+    setup_sale(deps.as_mut());
+
+    let now = mock_env().block.time.seconds() as u128;
+    let msg = ExecuteMsg::SetSaleConfig {
+        sale_config: SaleConfig {
+            hard_cap: Some(Uint128::new(264)),
+            soft_cap: Uint128::zero(),
+            soft_cap_deadline: Uint128::zero(),
+            min_purchase: Uint128::zero(),
+            max_allocation: None,
+            sale_start: Uint128::zero(),
+            sale_end: Some(Uint128::new(now + 100)),
+            pro_rata: true,
+        },
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), msg).unwrap();
+    let msg = ExecuteMsg::AddUser {
+        whitelist: None,
+        min_aqua_out: None,
+    };
+    for user in ["user1", "user2", "user3"] {
+        let info = mock_info(user, &[Coin::new(1, JUNO)]);
+        execute(deps.as_mut(), mock_env(), info, msg.clone()).unwrap();
+    }
+
+    let mut env = mock_env();
+    env.block.time = env.block.time.plus_seconds(101);
+    let admin = mock_info("admin", &[]);
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        admin.clone(),
+        ExecuteMsg::FinalizeSale {},
+    )
+    .unwrap();
+
+    //half filled payments of 1 are kept whole, the reserve stays behind
+    let msg = ExecuteMsg::Withdraw {
+        wallet: String::from("treasury"),
+    };
+    let res = execute(deps.as_mut(), env.clone(), admin, msg).unwrap();
+    assert_eq!(
+        res.messages[0].msg,
+        CosmosMsg::Bank(BankMsg::Send {
+            to_address: String::from("treasury"),
+            amount: vec![Coin::new(1, JUNO)],
+        })
+    );
+    for user in ["user1", "user2", "user3"] {
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(user, &[]),
+            ExecuteMsg::ClaimRefund {},
+        )
+        .unwrap_err();
+        assert!(matches!(err, ContractError::RefundNotAvailable {}));
+    }
+}
+
+#[test]
+fn user_history() {
+    let mut deps = mock_dependencies(&[]);
+    setup_sale(deps.as_mut());
+
+    let msg = ExecuteMsg::AddUser {
+        whitelist: None,
+        min_aqua_out: None,
+    };
+    for _ in 0..3 {
+        let info = mock_info("user", &[Coin::new(30, JUNO)]);
+        execute(deps.as_mut(), mock_env(), info, msg.clone()).unwrap();
+    }
+    let info = mock_info("user2", &[Coin::new(30, JUNO)]);
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let msg = ExecuteMsg::AddUserByOwner {
+        wallet: String::from("user"),
+        amount: Uint128::new(1000),
+        vest_param: None,
+        category: None,
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), msg).unwrap();
+
+    let msg = QueryMsg::GetUserHistory {
+        wallet: Addr::unchecked("user"),
+        start_after: None,
+        limit: Some(2),
+    };
+    let history: Vec<ContributionRecord> =
+        from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+    assert_eq!(history.len(), 2);
+    assert_eq!(
+        history[0],
+        ContributionRecord {
+            id: 1,
+            wallet: Addr::unchecked("user"),
+            denom: String::from(JUNO),
+            paid: Uint128::new(30),
+            price: Uint128::new(5280),
+            aqua_price: Uint128::new(30),
+            aqua_amount: Uint128::new(5280),
+            time: Uint128::new(mock_env().block.time.seconds() as u128),
+            source: ContributionSource::Purchase,
+            category: None,
+        }
+    );
+    assert_eq!(history[1].id, 2);
+
+    let msg = QueryMsg::GetUserHistory {
+        wallet: Addr::unchecked("user"),
+        start_after: Some(history[1].id),
+        limit: None,
+    };
+    let history: Vec<ContributionRecord> =
+        from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+    let ids: Vec<u64> = history.iter().map(|record| record.id).collect();
+    assert_eq!(ids, vec![3, 5]);
+    assert_eq!(history[1].source, ContributionSource::Owner);
+    assert_eq!(history[1].aqua_amount, Uint128::new(1000));
+    assert!(history[1].paid.is_zero());
+}
+
+#[test]
+fn escrow_claim() {
+    let mut deps = mock_dependencies(&[]);
+    setup_sale(deps.as_mut());
+
+    let msg = ExecuteMsg::AddUser {
+        whitelist: None,
+        min_aqua_out: None,
+    };
+    let info = mock_info("user", &[Coin::new(30, JUNO)]);
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let info = mock_info("admin", &[]);
+    let msg = ExecuteMsg::SetEscrow { enabled: true };
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    //only the AQUA token can fund the escrow
+    let deposit = |amount: u128| {
+        ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: String::from("treasury"),
+            amount: Uint128::new(amount),
+            msg: to_binary(&Cw20HookMsg::Deposit {}).unwrap(),
+        })
+    };
+    let info = mock_info("other token", &[]);
+    let err = execute(deps.as_mut(), mock_env(), info, deposit(6000)).unwrap_err();
+    assert!(matches!(err, ContractError::InvalidEscrowToken {}));
+
+    let info = mock_info("token", &[]);
+    execute(deps.as_mut(), mock_env(), info, deposit(6000)).unwrap();
+
+    let escrow: EscrowInfo =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::GetEscrow {}).unwrap()).unwrap();
+    assert_eq!(
+        escrow,
+        EscrowInfo {
+            enabled: true,
+            funded: Uint128::new(6000),
+            obligations: Uint128::new(5280),
+        }
+    );
+
+    //the surplus over the obligations can be taken back
+    let msg = ExecuteMsg::WithdrawEscrow {
+        wallet: String::from("treasury"),
+        amount: Uint128::new(1000),
+    };
+    let err = execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), msg).unwrap_err();
+    assert!(matches!(
+        err,
+        ContractError::ExceedEscrowSurplus { surplus } if surplus == Uint128::new(720)
+    ));
+    let msg = ExecuteMsg::WithdrawEscrow {
+        wallet: String::from("treasury"),
+        amount: Uint128::new(720),
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), msg).unwrap();
+
+    let msg = ExecuteMsg::StartRelease {
+        start_time: Uint128::new(mock_env().block.time.seconds() as u128),
+        require_solvency: None,
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), msg).unwrap();
+
+    //claims are paid from the contract, no treasury allowance involved
+    let mut env = mock_env();
+    env.block.time = env.block.time.plus_seconds(7_776_000);
+    let info = mock_info("user", &[]);
+    let res = execute(
+        deps.as_mut(),
+        env,
+        info,
+        ExecuteMsg::ClaimPendingTokens {
+            position: None,
+            recipient: None,
+            msg: None,
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages[0].msg,
+        CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: String::from("token"),
+            msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                recipient: String::from("user"),
+                amount: Uint128::new(5280),
+            })
+            .unwrap(),
+            funds: vec![],
+        })
+    );
+
+    let escrow: EscrowInfo =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::GetEscrow {}).unwrap()).unwrap();
+    assert!(escrow.funded.is_zero());
+    assert!(escrow.obligations.is_zero());
+}
+
+#[test]
+fn solvency_check() {
+    let mut deps = mock_dependencies(&[]);
+    setup_sale(deps.as_mut());
+
+    let msg = ExecuteMsg::AddUser {
+        whitelist: None,
+        min_aqua_out: None,
+    };
+    let info = mock_info("user", &[Coin::new(30, JUNO)]);
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    deps.querier.with_token_balances(&[(
+        &String::from("token"),
+        &[(&String::from("treasury"), &Uint128::new(5000))],
+    )]);
+    deps.querier.with_token_allowances(&[(
+        "token",
+        "treasury",
+        MOCK_CONTRACT_ADDR,
+        Uint128::new(3000),
+    )]);
+
+    let solvency: SolvencyInfo =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::GetSolvency {}).unwrap()).unwrap();
+    assert_eq!(
+        solvency,
+        SolvencyInfo {
+            total: Uint128::new(5280),
+            released: Uint128::zero(),
+            obligations: Uint128::new(5280),
+            escrow: false,
+            balance: Uint128::new(5000),
+            allowance: Uint128::new(3000),
+            balance_shortfall: Uint128::new(280),
+            allowance_shortfall: Uint128::new(2280),
+        }
+    );
+
+    let msg = ExecuteMsg::StartRelease {
+        start_time: Uint128::new(mock_env().block.time.seconds() as u128),
+        require_solvency: Some(true),
+    };
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("admin", &[]),
+        msg.clone(),
+    )
+    .unwrap_err();
+    assert!(matches!(
+        err,
+        ContractError::Insolvent {
+            balance_shortfall,
+            allowance_shortfall,
+        } if balance_shortfall == Uint128::new(280) && allowance_shortfall == Uint128::new(2280)
+    ));
+
+    deps.querier.with_token_balances(&[(
+        &String::from("token"),
+        &[(&String::from("treasury"), &Uint128::new(10_000))],
+    )]);
+    deps.querier.with_token_allowances(&[(
+        "token",
+        "treasury",
+        MOCK_CONTRACT_ADDR,
+        Uint128::new(5280),
+    )]);
+    execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), msg).unwrap();
+}
+
+#[test]
+fn distribute_pending() {
+    let mut deps = mock_dependencies(&[]);
+    setup_sale(deps.as_mut());
+
+    let msg = ExecuteMsg::AddUser {
+        whitelist: None,
+        min_aqua_out: None,
+    };
+    for user in ["user1", "user2", "user3"] {
+        let info = mock_info(user, &[Coin::new(30, JUNO)]);
+        execute(deps.as_mut(), mock_env(), info, msg.clone()).unwrap();
+    }
+
+    let msg = ExecuteMsg::StartRelease {
+        start_time: Uint128::new(mock_env().block.time.seconds() as u128),
+        require_solvency: None,
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), msg).unwrap();
+    deps.querier.with_token_balances(&[(
+        &String::from("token"),
+        &[(&String::from("treasury"), &Uint128::new(1_000_000))],
+    )]);
+
+    let mut env = mock_env();
+    env.block.time = env.block.time.plus_seconds(7_776_000);
+    let distribute = |start_after: Option<&str>| ExecuteMsg::DistributePending {
+        start_after: start_after.map(String::from),
+        limit: Some(2),
+    };
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("user1", &[]),
+        distribute(None),
+    )
+    .unwrap_err();
+    assert!(matches!(err, ContractError::Unauthorized {}));
+
+    let info = mock_info("admin", &[]);
+    let res = execute(deps.as_mut(), env.clone(), info.clone(), distribute(None)).unwrap();
+    assert_eq!(res.messages.len(), 2);
+    assert_eq!(res.attributes[2].value, "user2");
+
+    //resume after the last wallet of the previous batch
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        info.clone(),
+        distribute(Some("user2")),
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages[0].msg,
+        CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: String::from("token"),
+            msg: to_binary(&Cw20ExecuteMsg::TransferFrom {
+                owner: String::from("treasury"),
+                recipient: String::from("user3"),
+                amount: Uint128::new(5280),
+            })
+            .unwrap(),
+            funds: vec![],
+        })
+    );
+    assert_eq!(res.attributes[2].value, "");
+
+    //nothing is paid twice
+    let res = execute(deps.as_mut(), env.clone(), info, distribute(None)).unwrap();
+    assert!(res.messages.is_empty());
+    let err = execute(
+        deps.as_mut(),
+        env,
+        mock_info("user1", &[]),
+        ExecuteMsg::ClaimPendingTokens {
+            position: None,
+            recipient: None,
+            msg: None,
+        },
+    )
+    .unwrap_err();
+    assert!(matches!(err, ContractError::NoPendingTokens {}));
+}
+
+#[test]
+fn distribute_before_release() {
+    let mut deps = mock_dependencies(&[Coin::new(90, JUNO)]);
+    setup_sale(deps.as_mut());
+    oversubscribe_sale(deps.as_mut());
+
+    let mut env = mock_env();
+    env.block.time = env.block.time.plus_seconds(101);
+    let admin = mock_info("admin", &[]);
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        admin.clone(),
+        ExecuteMsg::FinalizeSale {},
+    )
+    .unwrap();
+
+    //only the refunds go out, the cut allocations are kept
+    let msg = ExecuteMsg::DistributePending {
+        start_after: None,
+        limit: None,
+    };
+    let res = execute(deps.as_mut(), env.clone(), admin.clone(), msg).unwrap();
+    assert_eq!(
+        res.messages
+            .iter()
+            .map(|msg| msg.msg.clone())
+            .collect::<Vec<_>>(),
+        vec![
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: String::from("user"),
+                amount: vec![Coin::new(11, JUNO)],
+            }),
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: String::from("user2"),
+                amount: vec![Coin::new(22, JUNO)],
+            }),
+        ]
+    );
+    assert_eq!(res.attributes[1].value, "0");
+
+    let msg = ExecuteMsg::StartRelease {
+        start_time: Uint128::new(mock_env().block.time.seconds() as u128 + 200),
+        require_solvency: None,
+    };
+    execute(deps.as_mut(), env.clone(), admin, msg).unwrap();
+    deps.querier.with_token_balances(&[(
+        &String::from("token"),
+        &[(&String::from("treasury"), &Uint128::new(1_000_000))],
+    )]);
+    env.block.time = env.block.time.plus_seconds(7_776_100);
+    let msg = ExecuteMsg::ClaimPendingTokens {
+        position: None,
+        recipient: None,
+        msg: None,
+    };
+    let res = execute(deps.as_mut(), env, mock_info("user2", &[]), msg).unwrap();
+    assert_eq!(
+        res.messages
+            .iter()
+            .map(|msg| msg.msg.clone())
+            .collect::<Vec<_>>(),
+        vec![CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: String::from("token"),
+            msg: to_binary(&Cw20ExecuteMsg::TransferFrom {
+                owner: String::from("treasury"),
+                recipient: String::from("user2"),
+                amount: Uint128::new(6_666),
+            })
+            .unwrap(),
+            funds: vec![],
+        })]
+    );
+}
+
+#[test]
+fn claim_to_recipient() {
+    let mut deps = mock_dependencies(&[]);
+    setup_sale(deps.as_mut());
+
+    let msg = ExecuteMsg::AddUser {
+        whitelist: None,
+        min_aqua_out: None,
+    };
+    let info = mock_info("user", &[Coin::new(60, JUNO)]);
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let msg = ExecuteMsg::StartRelease {
+        start_time: Uint128::new(mock_env().block.time.seconds() as u128),
+        require_solvency: None,
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), msg).unwrap();
+    deps.querier.with_token_balances(&[(
+        &String::from("token"),
+        &[(&String::from("treasury"), &Uint128::new(1_000_000))],
+    )]);
+
+    //half vested, claimed to another wallet
+    let mut env = mock_env();
+    env.block.time = env.block.time.plus_seconds(3_888_000);
+    let msg = ExecuteMsg::ClaimPendingTokens {
+        position: None,
+        recipient: Some(String::from("dao")),
+        msg: None,
+    };
+    let res = execute(deps.as_mut(), env, mock_info("user", &[]), msg).unwrap();
+    assert_eq!(
+        res.messages[0].msg,
+        CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: String::from("token"),
+            msg: to_binary(&Cw20ExecuteMsg::TransferFrom {
+                owner: String::from("treasury"),
+                recipient: String::from("dao"),
+                amount: Uint128::new(5280),
+            })
+            .unwrap(),
+            funds: vec![],
+        })
+    );
+
+    //the rest sent straight into a staking contract
+    let mut env = mock_env();
+    env.block.time = env.block.time.plus_seconds(7_776_000);
+    let hook = to_binary("stake").unwrap();
+    let msg = ExecuteMsg::ClaimPendingTokens {
+        position: None,
+        recipient: Some(String::from("staking")),
+        msg: Some(hook.clone()),
+    };
+    let res = execute(deps.as_mut(), env, mock_info("user", &[]), msg).unwrap();
+    assert_eq!(
+        res.messages[0].msg,
+        CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: String::from("token"),
+            msg: to_binary(&Cw20ExecuteMsg::SendFrom {
+                owner: String::from("treasury"),
+                contract: String::from("staking"),
+                amount: Uint128::new(5280),
+                msg: hook,
+            })
+            .unwrap(),
+            funds: vec![],
+        })
+    );
+}
+
+#[test]
+fn transfer_position() {
+    let mut deps = mock_dependencies(&[]);
+    setup_sale(deps.as_mut());
+
+    let msg = ExecuteMsg::AddUser {
+        whitelist: None,
+        min_aqua_out: None,
+    };
+    let info = mock_info("user", &[Coin::new(60, JUNO)]);
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let transfer = |amount: Option<u128>| ExecuteMsg::TransferPosition {
+        recipient: String::from("multisig"),
+        amount: amount.map(Uint128::new),
+    };
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("user", &[]),
+        transfer(None),
+    )
+    .unwrap_err();
+    assert!(matches!(err, ContractError::ReleaseNotStarted {}));
+
+    let msg = ExecuteMsg::StartRelease {
+        start_time: Uint128::new(mock_env().block.time.seconds() as u128),
+        require_solvency: None,
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), msg).unwrap();
+    deps.querier.with_token_balances(&[(
+        &String::from("token"),
+        &[(&String::from("treasury"), &Uint128::new(1_000_000))],
+    )]);
+
+    //claim the first half, then move half of what is left
+    let mut env = mock_env();
+    env.block.time = env.block.time.plus_seconds(3_888_000);
+    let msg = ExecuteMsg::ClaimPendingTokens {
+        position: None,
+        recipient: None,
+        msg: None,
+    };
+    execute(deps.as_mut(), env.clone(), mock_info("user", &[]), msg).unwrap();
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("user", &[]),
+        transfer(Some(2640)),
+    )
+    .unwrap();
+
+    let user_info = |deps: &OwnedDeps<_, _, _>, wallet: &str| -> UserInfo {
+        let msg = QueryMsg::GetUserInfo {
+            wallet: Addr::unchecked(wallet),
+        };
+        from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap()
+    };
+    let position = user_info(&deps, "multisig");
+    assert_eq!(position.total_amount, Uint128::new(5280));
+    assert_eq!(position.released_amount, Uint128::new(2640));
+    assert_eq!(position.bought_amount, Uint128::new(5280));
+    assert_eq!(user_info(&deps, "user").total_amount, Uint128::new(5280));
+
+    //both positions keep the vesting progress
+    let msg = QueryMsg::GetPendingTokens {
+        wallet: Addr::unchecked("multisig"),
+    };
+    let pending: Uint128 = from_binary(&query(deps.as_ref(), env.clone(), msg).unwrap()).unwrap();
+    assert!(pending.is_zero());
+
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("user", &[]),
+        transfer(Some(3000)),
+    )
+    .unwrap_err();
+    assert!(matches!(
+        err,
+        ContractError::InvalidAmount { unreleased } if unreleased == Uint128::new(2640)
+    ));
+
+    //the whole rest merges into the recipient position
+    execute(deps.as_mut(), env, mock_info("user", &[]), transfer(None)).unwrap();
+    let position = user_info(&deps, "multisig");
+    assert_eq!(position.total_amount, Uint128::new(10560));
+    assert_eq!(position.released_amount, Uint128::new(5280));
+    let msg = QueryMsg::GetUserInfo {
+        wallet: Addr::unchecked("user"),
+    };
+    assert!(query(deps.as_ref(), mock_env(), msg).is_err());
+
+    //a position cut by an oversubscribed pro-rata sale is not cut again
+    let mut deps = mock_dependencies(&[Coin::new(90, JUNO)]);
+    setup_sale(deps.as_mut());
+    oversubscribe_sale(deps.as_mut());
+
+    let mut env = mock_env();
+    env.block.time = env.block.time.plus_seconds(101);
+    let admin = mock_info("admin", &[]);
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        admin.clone(),
+        ExecuteMsg::FinalizeSale {},
+    )
+    .unwrap();
+    let msg = ExecuteMsg::StartRelease {
+        start_time: Uint128::new(mock_env().block.time.seconds() as u128 + 200),
+        require_solvency: None,
+    };
+    execute(deps.as_mut(), env.clone(), admin, msg).unwrap();
+
+    //the cut position moves once, with the refund going to the buyer
+    let msg = ExecuteMsg::TransferPosition {
+        recipient: String::from("fresh"),
+        amount: None,
+    };
+    let res = execute(deps.as_mut(), env.clone(), mock_info("user2", &[]), msg).unwrap();
+    assert_eq!(
+        res.messages[0].msg,
+        CosmosMsg::Bank(BankMsg::Send {
+            to_address: String::from("user2"),
+            amount: vec![Coin::new(22, JUNO)],
+        })
+    );
+
+    deps.querier.with_token_balances(&[(
+        &String::from("token"),
+        &[(&String::from("treasury"), &Uint128::new(1_000_000))],
+    )]);
+    env.block.time = env.block.time.plus_seconds(7_776_100);
+    let msg = ExecuteMsg::ClaimPendingTokens {
+        position: None,
+        recipient: None,
+        msg: None,
+    };
+    let res = execute(deps.as_mut(), env, mock_info("fresh", &[]), msg).unwrap();
+    assert_eq!(
+        res.messages
+            .iter()
+            .map(|msg| msg.msg.clone())
+            .collect::<Vec<_>>(),
+        vec![CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: String::from("token"),
+            msg: to_binary(&Cw20ExecuteMsg::TransferFrom {
+                owner: String::from("treasury"),
+                recipient: String::from("fresh"),
+                amount: Uint128::new(6_666),
+            })
+            .unwrap(),
+            funds: vec![],
+        })]
+    );
+}
+
+#[test]
+fn nft_positions() {
+    let mut deps = mock_dependencies(&[]);
+    setup_sale(deps.as_mut());
+
+    let msg = ExecuteMsg::AddUser {
+        whitelist: None,
+        min_aqua_out: None,
+    };
+    let info = mock_info("early", &[Coin::new(30, JUNO)]);
+    execute(deps.as_mut(), mock_env(), info, msg.clone()).unwrap();
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("early", &[]),
+        ExecuteMsg::MintPositionNft {},
+    )
+    .unwrap_err();
+    assert!(matches!(err, ContractError::NftDisabled {}));
+
+    let info = mock_info("admin", &[]);
+    let set_nft = ExecuteMsg::SetNftContract {
+        nft_addr: Some(String::from("nft")),
+    };
+    execute(deps.as_mut(), mock_env(), info, set_nft).unwrap();
+
+    //new positions are minted on purchase, existing ones on request
+    let mint_msg = |wallet: &str| {
+        CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: String::from("nft"),
+            msg: to_binary(&Cw721BaseExecuteMsg::Mint(MintMsg::<Extension> {
+                token_id: String::from(wallet),
+                owner: String::from(wallet),
+                token_uri: None,
+                extension: None,
+            }))
+            .unwrap(),
+            funds: vec![],
+        })
+    };
+    let info = mock_info("user", &[Coin::new(30, JUNO)]);
+    let res = execute(deps.as_mut(), mock_env(), info, msg.clone()).unwrap();
+    assert_eq!(res.messages[0].msg, mint_msg("user"));
+    deps.querier.with_nft_owners(&[("nft", "user", "user")]);
+    let info = mock_info("user", &[Coin::new(30, JUNO)]);
+    let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+    assert!(res.messages.is_empty());
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("early", &[]),
+        ExecuteMsg::MintPositionNft {},
+    )
+    .unwrap();
+    assert_eq!(res.messages[0].msg, mint_msg("early"));
+
+    let msg = ExecuteMsg::StartRelease {
+        start_time: Uint128::new(mock_env().block.time.seconds() as u128),
+        require_solvency: None,
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), msg).unwrap();
+    deps.querier.with_token_balances(&[(
+        &String::from("token"),
+        &[(&String::from("treasury"), &Uint128::new(1_000_000))],
+    )]);
+
+    //the position of user was sold to buyer
+    deps.querier
+        .with_nft_owners(&[("nft", "user", "buyer"), ("nft", "early", "early")]);
+    let msg = QueryMsg::GetPositionOwner {
+        wallet: Addr::unchecked("user"),
+    };
+    let owner: Addr = from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+    assert_eq!(owner, Addr::unchecked("buyer"));
+
+    //the seller can not add to the position now held by buyer
+    let msg = ExecuteMsg::AddUser {
+        whitelist: None,
+        min_aqua_out: None,
+    };
+    let info = mock_info("user", &[Coin::new(30, JUNO)]);
+    let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+    assert!(matches!(err, ContractError::PositionTransferred {}));
+    let msg = ExecuteMsg::AddUserByOwner {
+        wallet: String::from("user"),
+        amount: Uint128::new(100),
+        vest_param: None,
+        category: None,
+    };
+    let err = execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), msg).unwrap_err();
+    assert!(matches!(err, ContractError::PositionTransferred {}));
+
+    let mut env = mock_env();
+    env.block.time = env.block.time.plus_seconds(7_776_000);
+    let claim = |position: Option<&str>| ExecuteMsg::ClaimPendingTokens {
+        position: position.map(String::from),
+        recipient: None,
+        msg: None,
+    };
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("user", &[]),
+        claim(None),
+    )
+    .unwrap_err();
+    assert!(matches!(err, ContractError::Unauthorized {}));
+
+    let info = mock_info("buyer", &[]);
+    let res = execute(deps.as_mut(), env, info, claim(Some("user"))).unwrap();
+    assert_eq!(
+        res.messages[0].msg,
+        CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: String::from("token"),
+            msg: to_binary(&Cw20ExecuteMsg::TransferFrom {
+                owner: String::from("treasury"),
+                recipient: String::from("buyer"),
+                amount: Uint128::new(10560),
+            })
+            .unwrap(),
+            funds: vec![],
+        })
+    );
+}
+
+#[test]
+fn user_vesting_override() {
+    let mut deps = mock_dependencies(&[]);
+    setup_sale(deps.as_mut());
+
+    let msg = ExecuteMsg::AddUser {
+        whitelist: None,
+        min_aqua_out: None,
+    };
+    let info = mock_info("user", &[Coin::new(30, JUNO)]);
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let msg = ExecuteMsg::AddUserByOwner {
+        wallet: String::from("team"),
+        amount: Uint128::new(10_000),
+        vest_param: Some(VestingSchedule::Standard(VestingParameter {
+            soon: Uint128::new(10),
+            after: Uint128::new(1000),
+            period: Uint128::new(9000),
+        })),
+        category: None,
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), msg).unwrap();
+
+    let msg = ExecuteMsg::StartRelease {
+        start_time: Uint128::new(mock_env().block.time.seconds() as u128),
+        require_solvency: None,
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), msg).unwrap();
+
+    let mut env = mock_env();
+    env.block.time = env.block.time.plus_seconds(5500);
+    let pending = |deps: &OwnedDeps<_, _, _>, wallet: &str| -> Uint128 {
+        let msg = QueryMsg::GetPendingTokens {
+            wallet: Addr::unchecked(wallet),
+        };
+        from_binary(&query(deps.as_ref(), env.clone(), msg).unwrap()).unwrap()
+    };
+    assert_eq!(pending(&deps, "team"), Uint128::new(5500));
+    assert_eq!(pending(&deps, "user"), Uint128::new(3));
+
+    //without the override the wallet falls back to the global vesting
+    let msg = ExecuteMsg::SetUserVestingParameters {
+        wallet: String::from("team"),
+        params: None,
+    };
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("team", &[]),
+        msg.clone(),
+    )
+    .unwrap_err();
+    assert!(matches!(err, ContractError::Unauthorized {}));
+    execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), msg).unwrap();
+    assert_eq!(pending(&deps, "team"), Uint128::new(7));
+
+    //the override moves with the position to a new wallet
+    let lockup = VestingSchedule::Linear {
+        period: Uint128::new(1_000_000_000),
+    };
+    let msg = ExecuteMsg::SetUserVestingParameters {
+        wallet: String::from("team"),
+        params: Some(lockup.clone()),
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), msg).unwrap();
+    let msg = ExecuteMsg::TransferPosition {
+        recipient: String::from("heir"),
+        amount: None,
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("team", &[]), msg).unwrap();
+
+    let msg = QueryMsg::GetUserInfo {
+        wallet: Addr::unchecked("heir"),
+    };
+    let user_info: UserInfo = from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+    assert_eq!(user_info.vest_param, Some(lockup));
+    assert_eq!(pending(&deps, "heir"), Uint128::zero());
+}
+
+#[test]
+fn vesting_categories() {
+    let mut deps = mock_dependencies(&[]);
+    setup_sale(deps.as_mut());
+    let now = mock_env().block.time.seconds() as u128;
+
+    let team = Category {
+        vest_param: VestingSchedule::Linear {
+            period: Uint128::new(1000),
+        },
+        start_time: Some(Uint128::new(now + 100)),
+        cap: Some(Uint128::new(1000)),
+    };
+    let seed = Category {
+        vest_param: VestingSchedule::Linear {
+            period: Uint128::new(2000),
+        },
+        start_time: None,
+        cap: None,
+    };
+    for (name, category) in [("team", team.clone()), ("seed", seed)] {
+        let msg = ExecuteMsg::SetCategory {
+            name: String::from(name),
+            category,
+        };
+        execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), msg).unwrap();
+    }
+
+    let add = |amount: u128, category: &str| ExecuteMsg::AddUserByOwner {
+        wallet: String::from("alice"),
+        amount: Uint128::new(amount),
+        vest_param: None,
+        category: Some(String::from(category)),
+    };
+    let info = mock_info("admin", &[]);
+    execute(deps.as_mut(), mock_env(), info.clone(), add(800, "team")).unwrap();
+    execute(deps.as_mut(), mock_env(), info.clone(), add(1000, "seed")).unwrap();
+    let err = execute(deps.as_mut(), mock_env(), info.clone(), add(300, "team")).unwrap_err();
+    assert!(matches!(
+        err,
+        ContractError::CategoryCapExceeded { remaining } if remaining == Uint128::new(200)
+    ));
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        info.clone(),
+        add(300, "advisors"),
+    )
+    .unwrap_err();
+    assert!(matches!(err, ContractError::InvalidCategory {}));
+
+    let categories: Vec<CategoryInfo> =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::GetCategories {}).unwrap())
+            .unwrap();
+    assert_eq!(
+        categories[1],
+        CategoryInfo {
+            name: String::from("team"),
+            category: team.clone(),
+            allocated: Uint128::new(800),
+        }
+    );
+    let msg = ExecuteMsg::RemoveCategory {
+        name: String::from("team"),
+    };
+    let err = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap_err();
+    assert!(matches!(err, ContractError::CategoryHasAllocations {}));
+    let msg = ExecuteMsg::SetCategory {
+        name: String::from("team"),
+        category: Category {
+            cap: Some(Uint128::new(500)),
+            ..team
+        },
+    };
+    let err = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap_err();
+    assert!(matches!(err, ContractError::CategoryHasAllocations {}));
+
+    //team vests from its own start, seed only once the release started
+    let mut env = mock_env();
+    env.block.time = env.block.time.plus_seconds(600);
+    let pending = |deps: &OwnedDeps<_, _, _>| -> Uint128 {
+        let msg = QueryMsg::GetPendingTokens {
+            wallet: Addr::unchecked("alice"),
+        };
+        from_binary(&query(deps.as_ref(), env.clone(), msg).unwrap()).unwrap()
+    };
+    assert_eq!(pending(&deps), Uint128::new(400));
+
+    let msg = ExecuteMsg::StartRelease {
+        start_time: Uint128::new(now),
+        require_solvency: None,
+    };
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+    assert_eq!(pending(&deps), Uint128::new(700));
+}
+
+#[test]
+fn standard_schedule() {
+    let schedule = VestingSchedule::Standard(VestingParameter {
+        soon: Uint128::new(10),
+        after: Uint128::new(100),
+        period: Uint128::new(900),
+    });
+    let unlocked = |past_time: u128| calc_unlocked(Uint128::new(1000), &schedule, past_time.into());
+
+    //nothing before the start, soon percent right after it
+    assert_eq!(unlocked(0), Uint128::zero());
+    assert_eq!(unlocked(1), Uint128::new(100));
+    //the cliff holds the rest back
+    assert_eq!(unlocked(100), Uint128::new(100));
+    //then the rest unlocks linearly over the period
+    assert_eq!(unlocked(101), Uint128::new(101));
+    assert_eq!(unlocked(550), Uint128::new(550));
+    assert_eq!(unlocked(999), Uint128::new(999));
+    //up to the whole amount, including the soon part
+    assert_eq!(unlocked(1000), Uint128::new(1000));
+    assert_eq!(unlocked(100_000), Uint128::new(1000));
+}
+
+#[test]
+fn linear_schedule() {
+    let schedule = VestingSchedule::Linear {
+        period: Uint128::new(400),
+    };
+    let unlocked = |past_time: u128| calc_unlocked(Uint128::new(1000), &schedule, past_time.into());
+
+    assert_eq!(unlocked(0), Uint128::zero());
+    assert_eq!(unlocked(1), Uint128::new(2));
+    assert_eq!(unlocked(100), Uint128::new(250));
+    assert_eq!(unlocked(399), Uint128::new(997));
+    assert_eq!(unlocked(400), Uint128::new(1000));
+    assert_eq!(unlocked(100_000), Uint128::new(1000));
+}
+
+#[test]
+fn periodic_schedule() {
+    //10% every 30 days
+    let schedule = VestingSchedule::Periodic {
+        interval: Uint128::new(2_592_000),
+        steps: 10,
+    };
+    let unlocked = |past_time: u128| calc_unlocked(Uint128::new(1005), &schedule, past_time.into());
+
+    assert_eq!(unlocked(0), Uint128::zero());
+    assert_eq!(unlocked(2_591_999), Uint128::zero());
+    assert_eq!(unlocked(2_592_000), Uint128::new(100));
+    assert_eq!(unlocked(5_183_999), Uint128::new(100));
+    assert_eq!(unlocked(5_184_000), Uint128::new(201));
+    assert_eq!(unlocked(23_328_000), Uint128::new(904));
+    //the last step releases the rounding dust
+    assert_eq!(unlocked(25_920_000), Uint128::new(1005));
+    assert_eq!(unlocked(100_000_000), Uint128::new(1005));
+}
+
+#[test]
+fn invalid_schedules() {
+    let mut deps = mock_dependencies(&[]);
+    setup_sale(deps.as_mut());
+
+    let schedules = [
+        VestingSchedule::Standard(VestingParameter {
+            soon: Uint128::new(10),
+            after: Uint128::zero(),
+            period: Uint128::zero(),
+        }),
+        VestingSchedule::Standard(VestingParameter {
+            soon: Uint128::new(101),
+            after: Uint128::zero(),
+            period: Uint128::new(100),
+        }),
+        VestingSchedule::Linear {
+            period: Uint128::zero(),
+        },
+        VestingSchedule::Periodic {
+            interval: Uint128::zero(),
+            steps: 10,
+        },
+        VestingSchedule::Periodic {
+            interval: Uint128::new(100),
+            steps: 0,
+        },
+    ];
+    for params in schedules {
+        let msg = ExecuteMsg::SetVestingParameters { params };
+        let err = execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), msg).unwrap_err();
+        assert!(matches!(err, ContractError::InvalidSchedule {}));
+    }
+
+    //a periodic schedule drives the global vesting
+    let msg = ExecuteMsg::SetVestingParameters {
+        params: VestingSchedule::Periodic {
+            interval: Uint128::new(100),
+            steps: 4,
+        },
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), msg).unwrap();
+
+    let msg = ExecuteMsg::AddUserByOwner {
+        wallet: String::from("user"),
+        amount: Uint128::new(1000),
+        vest_param: None,
+        category: None,
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), msg).unwrap();
+    let msg = ExecuteMsg::StartRelease {
+        start_time: Uint128::new(mock_env().block.time.seconds() as u128),
+        require_solvency: None,
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), msg).unwrap();
+
+    let mut env = mock_env();
+    env.block.time = env.block.time.plus_seconds(250);
+    let msg = QueryMsg::GetPendingTokens {
+        wallet: Addr::unchecked("user"),
+    };
+    let pending: Uint128 = from_binary(&query(deps.as_ref(), env, msg).unwrap()).unwrap();
+    assert_eq!(pending, Uint128::new(500));
+}
+
+#[test]
+fn checkpoint_schedule() {
+    let checkpoint = |time: u128, unlocked: u64| Checkpoint {
+        time: Uint128::new(time),
+        unlocked,
+    };
+    let schedule = VestingSchedule::Checkpoints(vec![
+        checkpoint(100, 2000),
+        checkpoint(300, 2000),
+        checkpoint(400, 10_000),
+    ]);
+    let unlocked = |past_time: u128| calc_unlocked(Uint128::new(1000), &schedule, past_time.into());
+
+    //interpolated from nothing up to the first checkpoint
+    assert_eq!(unlocked(0), Uint128::zero());
+    assert_eq!(unlocked(50), Uint128::new(100));
+    assert_eq!(unlocked(100), Uint128::new(200));
+    //flat between equal checkpoints
+    assert_eq!(unlocked(200), Uint128::new(200));
+    assert_eq!(unlocked(300), Uint128::new(200));
+    assert_eq!(unlocked(350), Uint128::new(600));
+    assert_eq!(unlocked(400), Uint128::new(1000));
+    assert_eq!(unlocked(100_000), Uint128::new(1000));
+
+    //a checkpoint at the start unlocks right away
+    let schedule = VestingSchedule::Checkpoints(vec![checkpoint(0, 2500), checkpoint(100, 10_000)]);
+    assert_eq!(
+        calc_unlocked(Uint128::new(1000), &schedule, Uint128::zero()),
+        Uint128::new(250)
+    );
+
+    let mut deps = mock_dependencies(&[]);
+    setup_sale(deps.as_mut());
+    let invalid = [
+        vec![],
+        vec![checkpoint(100, 5000), checkpoint(100, 10_000)],
+        vec![checkpoint(100, 5000), checkpoint(50, 10_000)],
+        vec![
+            checkpoint(100, 5000),
+            checkpoint(200, 4000),
+            checkpoint(300, 10_000),
+        ],
+        vec![checkpoint(100, 5000), checkpoint(200, 9000)],
+        vec![checkpoint(100, 5000), checkpoint(200, 10_001)],
+    ];
+    for checkpoints in invalid {
+        let msg = ExecuteMsg::SetVestingParameters {
+            params: VestingSchedule::Checkpoints(checkpoints),
+        };
+        let err = execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), msg).unwrap_err();
+        assert!(matches!(err, ContractError::InvalidSchedule {}));
+    }
+
+    //usable globally and per wallet
+    let msg = ExecuteMsg::SetVestingParameters {
+        params: VestingSchedule::Checkpoints(vec![checkpoint(1000, 10_000)]),
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), msg).unwrap();
+    for wallet in ["user", "partner"] {
+        let msg = ExecuteMsg::AddUserByOwner {
+            wallet: String::from(wallet),
+            amount: Uint128::new(1000),
+            vest_param: None,
+            category: None,
+        };
+        execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), msg).unwrap();
+    }
+    let msg = ExecuteMsg::SetUserVestingParameters {
+        wallet: String::from("partner"),
+        params: Some(schedule),
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), msg).unwrap();
+    let msg = ExecuteMsg::StartRelease {
+        start_time: Uint128::new(mock_env().block.time.seconds() as u128),
+        require_solvency: None,
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), msg).unwrap();
+
+    let mut env = mock_env();
+    env.block.time = env.block.time.plus_seconds(40);
+    let pending = |wallet: &str| -> Uint128 {
+        let msg = QueryMsg::GetPendingTokens {
+            wallet: Addr::unchecked(wallet),
+        };
+        from_binary(&query(deps.as_ref(), env.clone(), msg).unwrap()).unwrap()
+    };
+    assert_eq!(pending("user"), Uint128::new(40));
+    assert_eq!(pending("partner"), Uint128::new(550));
+}
+
+#[test]
+fn revocable_vesting() {
+    let mut deps = mock_dependencies(&[]);
+    setup_sale(deps.as_mut());
+    let now = mock_env().block.time.seconds() as u128;
+
+    let msg = ExecuteMsg::SetCategory {
+        name: String::from("advisors"),
+        category: Category {
+            vest_param: VestingSchedule::Linear {
+                period: Uint128::new(1000),
+            },
+            start_time: Some(Uint128::new(now)),
+            cap: None,
+        },
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), msg).unwrap();
+
+    let msg = ExecuteMsg::AddUser {
+        whitelist: None,
+        min_aqua_out: None,
+    };
+    let info = mock_info("user", &[Coin::new(30, JUNO)]);
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let msg = ExecuteMsg::AddUserByOwner {
+        wallet: String::from("alice"),
+        amount: Uint128::new(400),
+        vest_param: None,
+        category: Some(String::from("advisors")),
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), msg).unwrap();
+    let msg = ExecuteMsg::AddUserByOwner {
+        wallet: String::from("alice"),
+        amount: Uint128::new(600),
+        vest_param: Some(VestingSchedule::Linear {
+            period: Uint128::new(2000),
+        }),
+        category: None,
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), msg).unwrap();
+
+    let msg = ExecuteMsg::StartRelease {
+        start_time: Uint128::new(now),
+        require_solvency: None,
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), msg).unwrap();
+    let obligations = |deps: &OwnedDeps<_, _, _>| -> Uint128 {
+        let escrow: EscrowInfo =
+            from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::GetEscrow {}).unwrap())
+                .unwrap();
+        escrow.obligations
+    };
+    let before = obligations(&deps);
+
+    //purchased allocations are not revocable
+    let mut env = mock_env();
+    env.block.time = env.block.time.plus_seconds(500);
+    let revoke = |wallet: &str| ExecuteMsg::RevokeUser {
+        wallet: String::from(wallet),
+    };
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("admin", &[]),
+        revoke("user"),
+    )
+    .unwrap_err();
+    assert!(matches!(err, ContractError::NotRevocable {}));
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("alice", &[]),
+        revoke("alice"),
+    )
+    .unwrap_err();
+    assert!(matches!(err, ContractError::Unauthorized {}));
+
+    //200 of the category and 150 of the rest vested, 650 go back
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("admin", &[]),
+        revoke("alice"),
+    )
+    .unwrap();
+    assert_eq!(res.attributes[1].value, "650");
+    assert_eq!(before - obligations(&deps), Uint128::new(650));
+
+    let msg = QueryMsg::GetUserInfo {
+        wallet: Addr::unchecked("alice"),
+    };
+    let user_info: UserInfo = from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+    assert_eq!(user_info.total_amount, Uint128::new(350));
+    assert_eq!(user_info.vested_amount, Uint128::new(350));
+    assert_eq!(user_info.revocable_amount, Uint128::zero());
+    assert!(user_info.category_amounts.is_empty());
+
+    let categories: Vec<CategoryInfo> =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::GetCategories {}).unwrap())
+            .unwrap();
+    assert_eq!(categories[0].allocated, Uint128::new(200));
+
+    //vesting stopped at the revocation
+    env.block.time = env.block.time.plus_seconds(5000);
+    let msg = QueryMsg::GetPendingTokens {
+        wallet: Addr::unchecked("alice"),
+    };
+    let pending: Uint128 = from_binary(&query(deps.as_ref(), env.clone(), msg).unwrap()).unwrap();
+    assert_eq!(pending, Uint128::new(350));
+
+    let err = execute(deps.as_mut(), env, mock_info("admin", &[]), revoke("alice")).unwrap_err();
+    assert!(matches!(err, ContractError::NotRevocable {}));
+}
+
+#[test]
+fn pause_flags() {
+    let mut deps = mock_dependencies(&[]);
+    setup_sale(deps.as_mut());
+
+    let msg = ExecuteMsg::SetPauser {
+        pauser: Some(String::from("guardian")),
+    };
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("guardian", &[]),
+        msg.clone(),
+    )
+    .unwrap_err();
+    assert!(matches!(err, ContractError::Unauthorized {}));
+    execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), msg).unwrap();
+
+    let pause = |sales: bool, claims: bool, admin: bool| ExecuteMsg::SetPause {
+        paused: PauseFlags {
+            sales,
+            claims,
+            admin,
+        },
+    };
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("user", &[]),
+        pause(true, false, false),
+    )
+    .unwrap_err();
+    assert!(matches!(err, ContractError::Unauthorized {}));
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("guardian", &[]),
+        pause(true, false, false),
+    )
+    .unwrap();
+
+    let status: PauseInfo =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::GetPauseStatus {}).unwrap())
+            .unwrap();
+    assert_eq!(
+        status,
+        PauseInfo {
+            pauser: Some(Addr::unchecked("guardian")),
+            paused: PauseFlags {
+                sales: true,
+                claims: false,
+                admin: false,
+            },
+        }
+    );
+
+    let add_user = ExecuteMsg::AddUser {
+        whitelist: None,
+        min_aqua_out: None,
+    };
+    let info = mock_info("user", &[Coin::new(30, JUNO)]);
+    let err = execute(deps.as_mut(), mock_env(), info.clone(), add_user.clone()).unwrap_err();
+    assert!(matches!(err, ContractError::Paused {}));
+
+    //the sale goes on while claims are halted
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("guardian", &[]),
+        pause(false, true, true),
+    )
+    .unwrap();
+    execute(deps.as_mut(), mock_env(), info, add_user).unwrap();
+
+    let msg = ExecuteMsg::StartRelease {
+        start_time: Uint128::new(mock_env().block.time.seconds() as u128),
+        require_solvency: None,
+    };
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("admin", &[]),
+        msg.clone(),
+    )
+    .unwrap_err();
+    assert!(matches!(err, ContractError::Paused {}));
+    let msg = ExecuteMsg::Withdraw {
+        wallet: String::from("treasury"),
+    };
+    let err = execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), msg).unwrap_err();
+    assert!(matches!(err, ContractError::Paused {}));
+
+    let msg = ExecuteMsg::ClaimPendingTokens {
+        position: None,
+        recipient: None,
+        msg: None,
+    };
+    let err = execute(deps.as_mut(), mock_env(), mock_info("user", &[]), msg).unwrap_err();
+    assert!(matches!(err, ContractError::Paused {}));
+}
+
+#[test]
+fn ownership_transfer() {
+    let mut deps = mock_dependencies(&[]);
+    setup_sale(deps.as_mut());
+    let now = mock_env().block.time.seconds() as u128;
+
+    let propose = |owner: &str, expires_at: Option<u128>| ExecuteMsg::ProposeNewOwner {
+        owner: String::from(owner),
+        expires_at: expires_at.map(Uint128::new),
+    };
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("treasury", &[]),
+        propose("newowner", None),
+    )
+    .unwrap_err();
+    assert!(matches!(err, ContractError::Unauthorized {}));
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("admin", &[]),
+        propose("newowner", Some(now)),
+    )
+    .unwrap_err();
+    assert!(matches!(err, ContractError::OwnershipProposalExpired {}));
+
+    //a proposal expires unaccepted
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("admin", &[]),
+        propose("newowner", Some(now + 100)),
+    )
+    .unwrap();
+    let mut env = mock_env();
+    env.block.time = env.block.time.plus_seconds(100);
+    let err = execute(
+        deps.as_mut(),
+        env,
+        mock_info("newowner", &[]),
+        ExecuteMsg::AcceptOwnership {},
+    )
+    .unwrap_err();
+    assert!(matches!(err, ContractError::OwnershipProposalExpired {}));
+
+    //a cancelled proposal can not be accepted
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("admin", &[]),
+        ExecuteMsg::CancelOwnershipProposal {},
+    )
+    .unwrap();
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("newowner", &[]),
+        ExecuteMsg::AcceptOwnership {},
+    )
+    .unwrap_err();
+    assert!(matches!(err, ContractError::NoOwnershipProposal {}));
+
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("admin", &[]),
+        propose("newowner", None),
+    )
+    .unwrap();
+    let proposal: Option<OwnershipProposal> =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::GetOwnershipProposal {}).unwrap())
+            .unwrap();
+    assert_eq!(
+        proposal,
+        Some(OwnershipProposal {
+            owner: Addr::unchecked("newowner"),
+            expires_at: None,
+        })
+    );
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("user", &[]),
+        ExecuteMsg::AcceptOwnership {},
+    )
+    .unwrap_err();
+    assert!(matches!(err, ContractError::NoOwnershipProposal {}));
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("newowner", &[]),
+        ExecuteMsg::AcceptOwnership {},
+    )
+    .unwrap();
+
+    let config: Config =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::GetConfig {}).unwrap()).unwrap();
+    assert_eq!(config.owner, Addr::unchecked("newowner"));
+    let proposal: Option<OwnershipProposal> =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::GetOwnershipProposal {}).unwrap())
+            .unwrap();
+    assert_eq!(proposal, None);
+
+    //the previous owner no longer configures the contract
+    let msg = ExecuteMsg::SetConfig {
+        treasury: String::from("treasury"),
+        token_addr: String::from("token"),
+    };
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("admin", &[]),
+        msg.clone(),
+    )
+    .unwrap_err();
+    assert!(matches!(err, ContractError::Unauthorized {}));
+    execute(deps.as_mut(), mock_env(), mock_info("newowner", &[]), msg).unwrap();
+}
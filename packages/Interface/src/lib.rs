@@ -1 +1,4 @@
-pub mod vesting;
\ No newline at end of file
+#![allow(non_snake_case)]
+
+pub mod oracle;
+pub mod vesting;
@@ -1,4 +1,5 @@
-use cosmwasm_std::{Addr, Uint128, Uint64};
+use cosmwasm_std::{Addr, Binary, Coin, Uint128};
+use cw20::{Cw20Coin, Cw20ReceiveMsg};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
@@ -7,6 +8,7 @@ pub struct InstantiateMsg {
     pub admin: Option<String>,
     pub token_addr: String,
     pub treasury: String,
+    pub accepted_denoms: Vec<AcceptedDenom>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -14,40 +16,176 @@ pub struct InstantiateMsg {
 pub enum ExecuteMsg {
     StartRelease {
         start_time: Uint128,
+        require_solvency: Option<bool>, //refuse to start while the obligations are not covered
     },
     SetConfig {
-        admin: String,
         treasury: String,
         token_addr: String,
-        start_time: Uint128,
     },
+    ProposeNewOwner {
+        owner: String,
+        expires_at: Option<Uint128>, //time the proposal can no longer be accepted, None for never
+    },
+    AcceptOwnership {},
+    CancelOwnershipProposal {},
     SetPrice {
-        usdc_price: Uint128,
-        juno_price: Uint128,
+        denom: String,
+        price: Uint128,
+    },
+    SetAcceptedDenom {
+        accepted_denom: AcceptedDenom,
+    },
+    RemoveAcceptedDenom {
+        denom: String,
+    },
+    SetOracle {
+        oracle: Option<OracleConfig>,
+    },
+    SetEscrow {
+        enabled: bool,
+    },
+    SetPauser {
+        pauser: Option<String>, //wallet allowed to pause besides the owner, None to remove it
+    },
+    SetPause {
+        paused: PauseFlags,
+    },
+    SetNftContract {
+        nft_addr: Option<String>, //cw721 minted by this contract, None to stop minting positions
+    },
+    MintPositionNft {},
+    WithdrawEscrow {
+        wallet: String,
+        amount: Uint128,
+    },
+    SetAcceptedCw20 {
+        accepted_cw20: AcceptedDenom,
+    },
+    RemoveAcceptedCw20 {
+        contract_addr: String,
     },
     SetVestingParameters {
-        params: VestingParameter,
+        params: VestingSchedule,
+    },
+    SetUserVestingParameters {
+        wallet: String,
+        params: Option<VestingSchedule>, //None to fall back to the round and global vesting
+    },
+    RevokeUser {
+        wallet: String,
+    },
+    SetSaleConfig {
+        sale_config: SaleConfig,
+    },
+    SetRound {
+        round_id: u64,
+        round: Round,
+    },
+    RemoveRound {
+        round_id: u64,
+    },
+    SetCategory {
+        name: String,
+        category: Category,
+    },
+    RemoveCategory {
+        name: String,
+    },
+    RegisterMerkleRoot {
+        merkle_root: Option<String>,
+    },
+    AddUser {
+        whitelist: Option<WhitelistProof>,
+        min_aqua_out: Option<Uint128>,
     },
-    AddUser {},
     AddUserByOwner {
         wallet: String,
         amount: Uint128,
+        vest_param: Option<VestingSchedule>, //vesting of the wallet, None to keep its current one
+        category: Option<String>, //category the amount is allocated in, None for no category
+    },
+    Receive(Cw20ReceiveMsg),
+    ClaimPendingTokens {
+        position: Option<String>, //wallet the position was created for, the sender by default
+        recipient: Option<String>, //wallet or contract receiving the tokens, the sender by default
+        msg: Option<Binary>,      //hook message to send the tokens into the recipient contract
+    },
+    DistributePending {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    ClaimRefund {},
+    FinalizeSale {},
+    TransferPosition {
+        recipient: String,
+        amount: Option<Uint128>, //unreleased AQUA amount to move, None for the whole position
     },
-    ClaimPendingTokens {},
     Withdraw {
         wallet: String,
     },
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Cw20HookMsg {
+    AddUser {
+        whitelist: Option<WhitelistProof>,
+        min_aqua_out: Option<Uint128>,
+    },
+    Deposit {},
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum QueryMsg {
     GetConfig {},
-    GetPendingTokens { wallet: Addr },
-    GetUserInfo { wallet: Addr },
-    GetBalance { wallet: Addr },
+    GetOwnershipProposal {},
+    GetPendingTokens {
+        wallet: Addr,
+    },
+    GetUserInfo {
+        wallet: Addr,
+    },
+    GetBalance {
+        wallet: Addr,
+    },
     GetAllInfo {},
-    GetPrice {},
+    GetPrice {
+        denom: String,
+    },
+    GetAcceptedDenoms {},
+    GetAcceptedCw20s {},
+    GetOracle {},
+    GetNftContract {},
+    GetPositionOwner {
+        wallet: Addr,
+    },
+    SimulatePurchase {
+        funds: Vec<Coin>,
+        cw20s: Vec<Cw20Coin>,
+    },
+    GetSaleConfig {},
+    GetSoldAmount {},
+    GetContributions {
+        wallet: Addr,
+    },
+    GetAllocation {
+        wallet: Addr,
+    },
+    GetSaleStatus {},
+    GetSaleResult {},
+    GetMerkleRoot {},
+    GetRounds {},
+    GetActiveRound {},
+    GetCategories {},
+    GetUserHistory {
+        wallet: Addr,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    GetEscrow {},
+    GetSolvency {},
+    GetPauseStatus {},
 }
 
 //------------Config---------------------------------------
@@ -59,6 +197,69 @@ pub struct Config {
     pub start_time: Uint128,
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct OwnershipProposal {
+    pub owner: Addr,
+    pub expires_at: Option<Uint128>,
+}
+
+//------------Accepted denom---------------------------------------
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct AcceptedDenom {
+    pub denom: String,  //native denom, or contract address of an accepted cw20
+    pub price: Uint128, //token price in the same unit as the AQUA price
+    pub decimals: u8,
+}
+
+//------------Oracle---------------------------------------
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct OracleConfig {
+    pub oracle_addr: String,
+    pub max_age: Uint128,       //max seconds since the last oracle price update
+    pub max_deviation: Uint128, //max percentage the oracle price may differ from the set price
+}
+
+//------------Escrow---------------------------------------
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct EscrowInfo {
+    pub enabled: bool, //claims are paid from the contract instead of the treasury allowance
+    pub funded: Uint128, //AQUA deposited into the contract and not yet claimed
+    pub obligations: Uint128, //AQUA bought or added and not yet released
+}
+
+//------------Pause---------------------------------------
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema, Default)]
+pub struct PauseFlags {
+    pub sales: bool,  //purchases with native coins or cw20s
+    pub claims: bool, //claims, distribution, refunds and position transfers
+    pub admin: bool,  //withdrawals, owner allocations, revocations, finalizing and release start
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PauseInfo {
+    pub pauser: Option<Addr>,
+    pub paused: PauseFlags,
+}
+
+//------------Solvency---------------------------------------
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct SolvencyInfo {
+    pub total: Uint128,
+    pub released: Uint128,
+    pub obligations: Uint128, //total minus released
+    pub escrow: bool,
+    pub balance: Uint128,   //AQUA held by the treasury, or funded in escrow mode
+    pub allowance: Uint128, //AQUA the treasury allows this contract to transfer
+    pub balance_shortfall: Uint128, //obligations not covered by the balance
+    pub allowance_shortfall: Uint128, //obligations not covered by the allowance, zero for escrow
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Contributions {
+    pub native: Vec<Coin>,
+    pub cw20: Vec<Cw20Coin>,
+}
+
 //------------Vesting parameter---------------------------------------
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema, Copy)]
 pub struct VestingParameter {
@@ -67,9 +268,142 @@ pub struct VestingParameter {
     pub period: Uint128,
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum VestingSchedule {
+    //soon percent at the start, the rest linearly over period after a cliff
+    Standard(VestingParameter),
+    //linearly over period from the start
+    Linear { period: Uint128 },
+    //equal tranches, one at the end of every interval
+    Periodic { interval: Uint128, steps: u64 },
+    //linearly interpolated between checkpoints, starting from nothing unlocked
+    Checkpoints(Vec<Checkpoint>),
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Checkpoint {
+    pub time: Uint128, //time offset from the vesting start
+    pub unlocked: u64, //cumulative unlocked basis points at this time
+}
+
+//------------Sale config---------------------------------------
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct SaleConfig {
+    pub hard_cap: Option<Uint128>, //max AQUA amount that can be sold, None for no limit
+    pub soft_cap: Uint128, //min AQUA amount to sell, otherwise contributors can claim refunds
+    pub soft_cap_deadline: Uint128, //time until which the soft cap has to be reached
+    pub min_purchase: Uint128, //min AQUA amount of a single AddUser purchase
+    pub max_allocation: Option<Uint128>, //max AQUA amount a wallet can hold, None for no limit
+    pub sale_start: Uint128, //time from which AddUser is accepted
+    pub sale_end: Option<Uint128>, //time after which AddUser is rejected, None for no end
+    pub pro_rata: bool, //accept contributions over the hard cap and allocate pro-rata after close
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct SaleResult {
+    pub sold: Uint128,      //AQUA amount bought during the sale
+    pub allocated: Uint128, //AQUA amount allocated after the pro-rata cut to the hard cap
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SaleStatus {
+    Upcoming,
+    Open,
+    Closed,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct AllocationInfo {
+    pub min_purchase: Uint128,
+    pub max_allocation: Option<Uint128>,
+    pub total_amount: Uint128,
+    pub remaining: Option<Uint128>, //AQUA amount the wallet can still buy, None for no limit
+}
+
+//------------Sale round---------------------------------------
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Round {
+    pub start_time: Uint128,
+    pub end_time: Uint128,
+    pub aqua_price: Uint128, //AQUA price in the same unit as the token prices
+    pub cap: Option<Uint128>, //max AQUA amount sold in this round, None for no limit
+    pub vest_param: Option<VestingSchedule>, //vesting of this round, None for the global one
+}
+
+//------------Vesting category---------------------------------------
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Category {
+    pub vest_param: VestingSchedule,
+    pub start_time: Option<Uint128>, //vesting start of the category, None for the release start
+    pub cap: Option<Uint128>,        //max AQUA amount allocated in this category, None for no limit
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct CategoryInfo {
+    pub name: String,
+    pub category: Category,
+    pub allocated: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct RoundInfo {
+    pub round_id: u64,
+    pub round: Round,
+    pub sold: Uint128,
+}
+
+//------------Whitelist---------------------------------------
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct WhitelistProof {
+    pub proof: Vec<String>, //hex encoded sibling hashes from the leaf up to the root
+    pub cap: Option<Uint128>, //AQUA cap encoded in the leaf together with the wallet
+}
+
 //-------------Token holder-------------------------------------------
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct UserInfo {
     pub total_amount: Uint128, //WFD token total amount that the investor buys.
     pub released_amount: Uint128, //released WFD token amount of totalAmount
+    pub bought_amount: Uint128, //part of totalAmount bought with funds, refundable if the sale fails
+    pub round_amounts: Vec<RoundAmount>, //part of boughtAmount bought in each round
+    pub category_amounts: Vec<CategoryAmount>, //part of totalAmount allocated in each category
+    pub revocable_amount: Uint128, //part of totalAmount added by the owner, revocable
+    pub vested_amount: Uint128, //part of totalAmount vested when revoked, unlocked already
+    pub vest_param: Option<VestingSchedule>, //vesting outside categories, overriding the others
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct CategoryAmount {
+    pub category: String,
+    pub amount: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct RoundAmount {
+    pub round_id: u64,
+    pub amount: Uint128,
+}
+
+//-------------Contribution ledger-------------------------------------------
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ContributionRecord {
+    pub id: u64,
+    pub wallet: Addr,
+    pub denom: String, //paid native denom or cw20 address, empty for AddUserByOwner
+    pub paid: Uint128, //amount paid in denom, zero for AddUserByOwner
+    pub price: Uint128, //token price used for the purchase, zero for AddUserByOwner
+    pub aqua_price: Uint128, //AQUA price used for the purchase, zero for AddUserByOwner
+    pub aqua_amount: Uint128, //AQUA amount credited to the wallet
+    pub time: Uint128,
+    pub source: ContributionSource,
+    pub category: Option<String>, //category of an AddUserByOwner allocation
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ContributionSource {
+    Purchase,
+    Owner,
 }